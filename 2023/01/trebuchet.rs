@@ -14,10 +14,10 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn calculate_sums(lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<(u32, u32), Box<dyn std::error::Error>> {
     let mut sum_part_1: u32 = 0;
     let mut sum_part_2: u32 = 0;
-    for line in lines {
+    for (i, line) in lines.enumerate() {
         let line = line?;
-        sum_part_1 += recover_digit_calibration_value(&line);
-        sum_part_2 += recover_spelled_calibration_value(&line);
+        sum_part_1 = sum_part_1.checked_add(recover_digit_calibration_value(&line)).ok_or(format!("line {}: sum overflows", i + 1))?;
+        sum_part_2 = sum_part_2.checked_add(recover_spelled_calibration_value(&line)).ok_or(format!("line {}: sum overflows", i + 1))?;
     }
     Ok((sum_part_1, sum_part_2))
 }
@@ -27,14 +27,14 @@ fn recover_digit_calibration_value(line: &str) -> u32 {
     let mut first_digit: u32 = 0;
     let mut last_digit: u32 = 0;
     for c in line.chars() {
-        if c.is_digit(10) {
-            first_digit = c.to_digit(10).unwrap();
+        if let Some(digit) = c.to_digit(10) {
+            first_digit = digit;
             break;
         }
     }
     for c in line.chars().rev() {
-        if c.is_digit(10) {
-            last_digit = c.to_digit(10).unwrap();
+        if let Some(digit) = c.to_digit(10) {
+            last_digit = digit;
             break;
         }
     }
@@ -45,15 +45,16 @@ fn recover_digit_calibration_value(line: &str) -> u32 {
 fn recover_spelled_calibration_value(line: &str) -> u32 {
     let mut first_digit: u32 = 0;
     let mut last_digit: u32 = 0;
-    for (i, _) in line.chars().enumerate() {
+    // slice at byte offsets of characters, so lines with multibyte characters are split on character boundaries
+    for (i, _) in line.char_indices() {
         if let Some(digit) = match_digit(&line[i..]) {
             first_digit = digit;
             break;
         }
     }
 
-    for (i, _) in line.chars().rev().enumerate() {
-        if let Some(digit) = match_late_digit(&line[..line.len() - i]) {
+    for (i, c) in line.char_indices().rev() {
+        if let Some(digit) = match_late_digit(&line[..i + c.len_utf8()]) {
             last_digit = digit;
             break;
         }
@@ -64,14 +65,9 @@ fn recover_spelled_calibration_value(line: &str) -> u32 {
 
 fn match_digit(substr: &str) -> Option<u32> {
     let mut chrs = substr.chars();
-    let(c0, c1, c2, c3, c4) = match substr.len() {
-        0 => ('_',  '_', '_', '_', '_'),
-        1 => (chrs.next().unwrap(), '_', '_', '_', '_'),
-        2 => (chrs.next().unwrap(), chrs.next().unwrap(), '_', '_', '_'),
-        3 => (chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap(), '_', '_'),
-        4 => (chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap(),  chrs.next().unwrap(), '_'),
-        _ => (chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap(),  chrs.next().unwrap(), chrs.next().unwrap()),
-    };
+    // characters past the end of substr read as '_'
+    let mut next = || chrs.next().unwrap_or('_');
+    let (c0, c1, c2, c3, c4) = (next(), next(), next(), next(), next());
     match (c0, c1, c2, c3, c4) {
         ('0', _, _, _, _) => Some(0),
         ('1', _, _, _, _) => Some(1),
@@ -98,14 +94,9 @@ fn match_digit(substr: &str) -> Option<u32> {
 
 fn match_late_digit(substr: &str) -> Option<u32> {
     let mut chrs = substr.chars().rev();
-    let(c0, c1, c2, c3, c4) = match substr.len() {
-        0 => ('_',  '_', '_', '_', '_'),
-        1 => (chrs.next().unwrap(), '_', '_', '_', '_'),
-        2 => (chrs.next().unwrap(), chrs.next().unwrap(), '_', '_', '_'),
-        3 => (chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap(), '_', '_'),
-        4 => (chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap(), '_'),
-        _ => (chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap(), chrs.next().unwrap()),
-    };
+    // characters past the end of substr read as '_'
+    let mut next = || chrs.next().unwrap_or('_');
+    let (c0, c1, c2, c3, c4) = (next(), next(), next(), next(), next());
     match (c0, c1, c2, c3, c4) {
         ('0', _, _, _, _) => Some(0),
         ('1', _, _, _, _) => Some(1),
@@ -240,4 +231,87 @@ mod tests {
         let lines_second = vec!["two1nine", "eightwothree", "abcone2threexyz", "xtwone3four", "4nineeightseven2", "zoneight234", "7pqrstsixteen"];
         assert_eq!(calculate_sums(lines_second.into_iter().map(|s| Ok(s.to_string()))).unwrap(), (209, 281));
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_multibyte_lines() {
+        assert_eq!(match_digit("\u{e9}\u{e9}"), None);
+        assert_eq!(match_late_digit("\u{e9}\u{e9}"), None);
+        assert_eq!(recover_spelled_calibration_value("\u{e9}two\u{1f600}"), 22);
+        assert_eq!(recover_spelled_calibration_value("\u{e9}\u{e9}1\u{e9}nine\u{e9}"), 19);
+        assert_eq!(recover_digit_calibration_value("\u{663}7\u{e9}"), 77);
+        assert_eq!(calculate_sums(["\u{e9}", "3\u{e9}\u{e9}"].into_iter().map(|s| Ok(s.to_string()))).unwrap(), (33, 33));
+    }
+
+    // The days share no code, so this xorshift generator is a copy local to this crate.
+    // Fixed seeds generate the same lines on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    const WORDS: [&str; 10] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine"];
+    // none of these letters occur in a spelled digit, so filler never forms one by accident
+    const FILLER: [char; 8] = ['a', 'b', 'c', 'd', 'k', 'l', 'm', 'p'];
+
+    // random_line writes digits and spelled digits separated by filler and returns it with the digits in order,
+    // each marked whether it is spelled
+    fn random_line(rng: &mut Rng) -> (String, Vec<(u32, bool)>) {
+        let mut line = String::new();
+        let mut digits = Vec::new();
+        for _ in 0..rng.below(6) {
+            for _ in 0..1 + rng.below(3) {
+                line.push(FILLER[rng.below(FILLER.len())]);
+            }
+            let digit = 1 + rng.below(9) as u32;
+            let spelled = rng.below(2) == 0;
+            if spelled {
+                line += WORDS[digit as usize];
+            } else {
+                line += &digit.to_string();
+            }
+            digits.push((digit, spelled));
+        }
+        (line, digits)
+    }
+
+    fn value(digits: &[u32]) -> u32 {
+        match (digits.first(), digits.last()) {
+            (Some(first), Some(last)) => first * 10 + last,
+            _ => 0,
+        }
+    }
+
+    #[test]
+    fn test_random_lines() {
+        let mut rng = Rng(0x7eb);
+        let mut lines = Vec::new();
+        let (mut sum_part_1, mut sum_part_2) = (0, 0);
+        for _ in 0..2000 {
+            let (line, digits) = random_line(&mut rng);
+            let ascii: Vec<u32> = digits.iter().filter(|(_, spelled)| !spelled).map(|(digit, _)| *digit).collect();
+            let all: Vec<u32> = digits.iter().map(|(digit, _)| *digit).collect();
+            // part 1 only sees the first and last ASCII digit, part 2 sees spelled digits too
+            assert_eq!(recover_digit_calibration_value(&line), value(&ascii), "{}", line);
+            assert_eq!(recover_spelled_calibration_value(&line), value(&all), "{}", line);
+
+            // spelling a digit out or writing it as a digit does not change the spelled value
+            let written = WORDS.iter().enumerate().fold(line.clone(), |line, (digit, word)| line.replace(word, &digit.to_string()));
+            assert_eq!(recover_digit_calibration_value(&written), value(&all), "{}", written);
+            assert_eq!(recover_spelled_calibration_value(&written), value(&all), "{}", written);
+            let spelled = all.iter().fold(written.clone(), |line, digit| line.replacen(&digit.to_string(), WORDS[*digit as usize], 1));
+            assert_eq!(recover_digit_calibration_value(&spelled), 0, "{}", spelled);
+            assert_eq!(recover_spelled_calibration_value(&spelled), value(&all), "{}", spelled);
+
+            sum_part_1 += value(&ascii);
+            sum_part_2 += value(&all);
+            lines.push(line);
+        }
+        assert_eq!(calculate_sums(lines.into_iter().map(Ok)).unwrap(), (sum_part_1, sum_part_2));
+    }
+}
@@ -16,28 +16,63 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
 fn evaluate_games(lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<(u32, u32), Box<dyn std::error::Error>> {
     let mut sum_of_possible_game_ids: u32 = 0;
     let mut sum_of_powers: u32 = 0;
-    for line in lines {
+    for (i, line) in lines.enumerate() {
         let line = line?;
-        let (game_id, possible) = eval_game_part_1(&line, (12, 13, 14)).ok_or("invalid line")?;
-        let (_, power) = eval_game_part_2(&line).ok_or("invalid line")?;
+        let (game_id, possible) = eval_game_part_1(&line, (12, 13, 14)).map_err(|e| format!("line {}: {}", i + 1, e))?;
+        let (_, power) = eval_game_part_2(&line).map_err(|e| format!("line {}: {}", i + 1, e))?;
         if possible {
-            sum_of_possible_game_ids +=  game_id;
+            sum_of_possible_game_ids = sum_of_possible_game_ids.checked_add(game_id).ok_or(format!("line {}: sum of game ids overflows", i + 1))?;
         }
-        sum_of_powers += power;
+        sum_of_powers = sum_of_powers.checked_add(power).ok_or(format!("line {}: sum of powers overflows", i + 1))?;
     }
     Ok((sum_of_possible_game_ids, sum_of_powers))
 }
 
-fn eval_game_part_1(line: &str, constraints: (u32, u32, u32)) -> Option<(u32, bool)>{
-    let (game_id, reveals) = line[5..].split_once(":")?;
-    let game_id = game_id.parse::<u32>().ok()?;
+#[derive(Debug, PartialEq)]
+enum ParseError {
+    // the line does not start with "Game N:"
+    MissingGameId,
+    InvalidGameId(String),
+    // a count is not of the form "N color"
+    InvalidCount(String),
+    // the power of the game does not fit into a u32
+    PowerOverflow,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ParseError::MissingGameId => write!(f, "expected \"Game N:\""),
+            ParseError::InvalidGameId(game_id) => write!(f, "invalid game id {:?}", game_id),
+            ParseError::InvalidCount(count) => write!(f, "invalid count {:?}, expected \"N color\"", count),
+            ParseError::PowerOverflow => write!(f, "power of the game overflows"),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+// read_game_id splits a line into its game id and the reveals after the colon
+fn read_game_id(line: &str) -> Result<(u32, &str), ParseError> {
+    let (game_id, reveals) = line.strip_prefix("Game ").and_then(|rest| rest.split_once(':')).ok_or(ParseError::MissingGameId)?;
+    let game_id = game_id.parse::<u32>().map_err(|_| ParseError::InvalidGameId(game_id.to_string()))?;
+    Ok((game_id, reveals))
+}
+
+fn read_count(count: &str) -> Result<(u32, &str), ParseError> {
+    let invalid = || ParseError::InvalidCount(count.trim().to_string());
+    let (number, color) = count.trim().split_once(' ').ok_or_else(invalid)?;
+    Ok((number.parse::<u32>().map_err(|_| invalid())?, color))
+}
+
+fn eval_game_part_1(line: &str, constraints: (u32, u32, u32)) -> Result<(u32, bool), ParseError> {
+    let (game_id, reveals) = read_game_id(line)?;
     let reveals = reveals.split(";");
     for reveal in reveals {
         let counts = reveal.split(",");
         let (mut red, mut green, mut blue) = (0, 0, 0);
         for count in counts {
-            let (count, color) = count.trim().split_once(" ")?;
-            let count = count.parse::<u32>().ok()?;
+            let (count, color) = read_count(count)?;
             match color {
                 "red" => red = count,
                 "green" => green = count,
@@ -46,23 +81,21 @@ fn eval_game_part_1(line: &str, constraints: (u32, u32, u32)) -> Option<(u32, bo
             }
         }
         if constraints.0 < red || constraints.1 < green || constraints.2 < blue {
-            return Some((game_id, false));
+            return Ok((game_id, false));
         }
     }
-    Some((game_id, true))
+    Ok((game_id, true))
 }
 
-fn eval_game_part_2(line: &str) -> Option<(u32, u32)>{
-    let (game_id, reveals) = line[5..].split_once(":")?;
-    let game_id = game_id.parse::<u32>().ok()?;
+fn eval_game_part_2(line: &str) -> Result<(u32, u32), ParseError> {
+    let (game_id, reveals) = read_game_id(line)?;
     let reveals = reveals.split(";");
-    let (mut req_red, mut req_green, mut req_blue) = (0, 0, 0);
+    let (mut req_red, mut req_green, mut req_blue): (u32, u32, u32) = (0, 0, 0);
     for reveal in reveals {
         let counts = reveal.split(",");
         let (mut red, mut green, mut blue) = (0, 0, 0);
         for count in counts {
-            let (count, color) = count.trim().split_once(" ")?;
-            let count = count.parse::<u32>().ok()?;
+            let (count, color) = read_count(count)?;
             match color {
                 "red" => red = count,
                 "green" => green = count,
@@ -74,5 +107,118 @@ fn eval_game_part_2(line: &str) -> Option<(u32, u32)>{
         req_green = max(req_green, green);
         req_blue = max(req_blue, blue);
     }
-    Some((game_id, req_red * req_green * req_blue))
-}
\ No newline at end of file
+    let power = req_red.checked_mul(req_green).and_then(|power| power.checked_mul(req_blue)).ok_or(ParseError::PowerOverflow)?;
+    Ok((game_id, power))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"Game 1: 3 blue, 4 red; 1 red, 2 green, 6 blue; 2 green
+Game 2: 1 blue, 2 green; 3 green, 4 blue, 1 red; 1 green, 1 blue
+Game 3: 8 green, 6 blue, 20 red; 5 blue, 4 red, 13 green; 5 green, 1 red
+Game 4: 1 green, 3 red, 6 blue; 3 green, 6 red; 3 green, 15 blue, 14 red
+Game 5: 6 red, 1 blue, 3 green; 2 blue, 1 red, 2 green"#;
+
+    fn lines(input: &str) -> impl Iterator<Item = Result<String, io::Error>> + '_ {
+        input.lines().map(|s| Ok(s.to_string()))
+    }
+
+    // Per-crate copy of the xorshift generator, seeded so the random games repeat on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: u32) -> u32 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as u32
+        }
+    }
+
+    // random_game writes a game line and returns it with the largest count shown of every color
+    fn random_game(rng: &mut Rng, game_id: u32) -> (String, (u32, u32, u32)) {
+        let mut most = (0, 0, 0);
+        let mut reveals = Vec::new();
+        for _ in 0..1 + rng.below(4) {
+            let mut colors = vec!["red", "green", "blue"];
+            // drop some colors and shuffle the rest
+            for _ in 0..rng.below(3) {
+                colors.remove(rng.below(colors.len() as u32) as usize);
+            }
+            for i in (1..colors.len()).rev() {
+                colors.swap(i, rng.below(i as u32 + 1) as usize);
+            }
+            let mut counts = Vec::new();
+            for color in colors {
+                let count = rng.below(21);
+                match color {
+                    "red" => most.0 = max(most.0, count),
+                    "green" => most.1 = max(most.1, count),
+                    _ => most.2 = max(most.2, count),
+                }
+                counts.push(format!("{} {}", count, color));
+            }
+            reveals.push(counts.join(", "));
+        }
+        (format!("Game {}: {}", game_id, reveals.join("; ")), most)
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(evaluate_games(lines(EXAMPLE)).unwrap(), (8, 2286));
+        assert_eq!(evaluate_games(lines(include_str!("input.txt"))).unwrap(), (2101, 58269));
+    }
+
+    #[test]
+    fn test_smallest_bag_makes_every_game_possible() {
+        let mut rng = Rng(0xc0be);
+        for game_id in 1..2000 {
+            let (line, (red, green, blue)) = random_game(&mut rng, game_id);
+            assert_eq!(eval_game_part_1(&line, (red, green, blue)), Ok((game_id, true)), "{}", line);
+            // any larger bag works as well
+            let larger = (red + rng.below(5), green + rng.below(5), blue + rng.below(5));
+            assert_eq!(eval_game_part_1(&line, larger), Ok((game_id, true)), "{}", line);
+            // one cube less of a color that was shown and the game is impossible
+            for (i, count) in [red, green, blue].into_iter().enumerate() {
+                if count == 0 {
+                    continue;
+                }
+                let mut smaller = [red, green, blue];
+                smaller[i] -= 1;
+                assert_eq!(eval_game_part_1(&line, (smaller[0], smaller[1], smaller[2])), Ok((game_id, false)), "{}", line);
+            }
+            assert_eq!(eval_game_part_2(&line), Ok((game_id, red * green * blue)));
+        }
+    }
+
+    #[test]
+    fn test_evaluate_games_sums() {
+        let mut rng = Rng(0xba9);
+        for _ in 0..50 {
+            let games: Vec<(String, (u32, u32, u32))> = (1..=rng.below(40)).map(|game_id| random_game(&mut rng, game_id)).collect();
+            let possible: u32 = (1..).zip(&games).filter(|(_, (_, most))| most.0 <= 12 && most.1 <= 13 && most.2 <= 14).map(|(game_id, _)| game_id).sum();
+            let powers: u32 = games.iter().map(|(_, most)| most.0 * most.1 * most.2).sum();
+            let input: Vec<&str> = games.iter().map(|(line, _)| line.as_str()).collect();
+            assert_eq!(evaluate_games(lines(&input.join("\n"))).unwrap(), (possible, powers));
+        }
+    }
+
+    #[test]
+    fn test_invalid_lines() {
+        assert_eq!(eval_game_part_1("", (12, 13, 14)), Err(ParseError::MissingGameId));
+        assert_eq!(eval_game_part_1("20 r", (12, 13, 14)), Err(ParseError::MissingGameId));
+        assert_eq!(eval_game_part_1("Game 1 3 blue", (12, 13, 14)), Err(ParseError::MissingGameId));
+        assert_eq!(eval_game_part_1("Game x: 3 blue", (12, 13, 14)), Err(ParseError::InvalidGameId("x".to_string())));
+        assert_eq!(eval_game_part_1("Game 1: 1!red", (12, 13, 14)), Err(ParseError::InvalidCount("1!red".to_string())));
+        assert_eq!(eval_game_part_2("Game 1: 3 blue;"), Err(ParseError::InvalidCount("".to_string())));
+        assert_eq!(eval_game_part_2("Game 1: 65536 red, 65536 green, 1 blue"), Err(ParseError::PowerOverflow));
+        assert_eq!(eval_game_part_2("Gamé 1: 3 blue"), Err(ParseError::MissingGameId));
+
+        let error = evaluate_games(lines("Game 1: 3 blue\nGame 2: 3 bl\u{fc}e, x red")).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: invalid count \"x red\", expected \"N color\"");
+        let error = evaluate_games(lines("Game 4294967295: 1 red\nGame 1: 1 red")).unwrap_err();
+        assert_eq!(error.to_string(), "line 2: sum of game ids overflows");
+    }
+}
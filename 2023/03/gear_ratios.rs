@@ -1,15 +1,21 @@
 use std::io::{self, BufRead};
 
 const MATRIX_SIZE: usize = 140;
+// read_matrix rejects longer part numbers, so part numbers fit into a u32 and gear ratios into a u64
+const MAX_PART_NUMBER_DIGITS: usize = 6;
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let stdin = io::stdin();
     let lines = stdin.lock().lines();
 
-    let matrix = read_matrix(lines)?;
-
-    let sum_part_1 = find_all_numbers(&matrix);
-    let sum_part_2 = find_gears(&matrix);
+    // --stream scans the schematic with a three row window instead of loading it as a whole,
+    // which supports schematics of any height.
+    let (sum_part_1, sum_part_2) = if std::env::args().any(|arg| arg == "--stream") {
+        scan_rows(lines)?
+    } else {
+        let matrix = read_matrix(lines)?;
+        (find_all_numbers(&matrix), find_gears(&matrix))
+    };
 
     println!("sum of part numbers: {}", sum_part_1);
     println!("sum of gear ratios: {}", sum_part_2);
@@ -17,12 +23,48 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+#[derive(Debug, PartialEq)]
+enum SchematicError {
+    // rows and columns are 1-based
+    TooLarge { row: usize, column: usize },
+    NumberTooLong { row: usize, column: usize },
+    Overflow { row: usize },
+}
+
+impl std::fmt::Display for SchematicError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            SchematicError::TooLarge { row, column } => {
+                write!(f, "row {}, column {}: schematic is larger than {}x{} (use --stream)", row, column, MATRIX_SIZE, MATRIX_SIZE)
+            }
+            // read_matrix and scan_rows share this error, so it names both limits
+            SchematicError::NumberTooLong { row, column } => write!(
+                f, "row {}, column {}: part number is too long, the limit is {} digits or {} with --stream",
+                row, column, MAX_PART_NUMBER_DIGITS, u64::MAX,
+            ),
+            SchematicError::Overflow { row } => write!(f, "row {}: sum overflows", row),
+        }
+    }
+}
+
+impl std::error::Error for SchematicError {}
+
+#[allow(clippy::explicit_counter_loop)]
 fn read_matrix(lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<[[char; MATRIX_SIZE]; MATRIX_SIZE], Box<dyn std::error::Error>> {
-    let mut matrix: [[char; MATRIX_SIZE]; MATRIX_SIZE] = [['\0'; MATRIX_SIZE]; MATRIX_SIZE];
+    // cells outside of the schematic are padded with '.' so they are never mistaken for symbols
+    let mut matrix: [[char; MATRIX_SIZE]; MATRIX_SIZE] = [['.'; MATRIX_SIZE]; MATRIX_SIZE];
     for (i, line) in lines.enumerate() {
         let line = line?;
         let mut j = 0;
+        let mut digits = 0;
         for c in line.chars() {
+            if i >= MATRIX_SIZE || j >= MATRIX_SIZE {
+                return Err(SchematicError::TooLarge { row: i + 1, column: j + 1 }.into());
+            }
+            digits = if c.is_ascii_digit() { digits + 1 } else { 0 };
+            if digits > MAX_PART_NUMBER_DIGITS {
+                return Err(SchematicError::NumberTooLong { row: i + 1, column: j + 2 - digits }.into());
+            }
             matrix[i][j] = c;
             j += 1;
         }
@@ -30,8 +72,8 @@ fn read_matrix(lines: impl Iterator<Item = Result<String, io::Error>>) -> Result
     Ok(matrix)
 }
 
-fn find_gears(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE]) -> u32 {
-    let mut sum: u32 = 0;
+fn find_gears(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE]) -> u64 {
+    let mut sum: u64 = 0;
     let (mut i, mut j) = (0, 0);
     while let Some((next_i, next_j, gear_ratio)) = find_next_gear(matrix, i, j) {
         i = next_i;
@@ -43,8 +85,9 @@ fn find_gears(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE]) -> u32 {
     sum
 }
 
-fn find_all_numbers(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE]) -> u32 {
-    let mut sum: u32 = 0;
+#[allow(clippy::is_digit_ascii_radix)]
+fn find_all_numbers(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE]) -> u64 {
+    let mut sum: u64 = 0;
     let (mut i, mut j) = (0, 0);
     while let Some((next_i, next_j)) = find_next_digit(matrix, i, j) {
         i = next_i;
@@ -55,7 +98,7 @@ fn find_all_numbers(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE]) -> u32 {
             j_end += 1;
         }
         if cell_range_has_symbol_neighbor(matrix, i, j_start, j_end) {
-            sum += matrix[i][j_start..=j_end].iter().collect::<String>().parse::<u32>().unwrap();
+            sum += matrix[i][j_start..=j_end].iter().collect::<String>().parse::<u64>().unwrap();
         }
         i = if j_end == (MATRIX_SIZE - 1) { i + 1 } else { i };
         j = (j_end + 1) % MATRIX_SIZE;
@@ -63,7 +106,7 @@ fn find_all_numbers(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE]) -> u32 {
     sum
 }
 
-fn find_next_gear(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], mut i: usize, mut j: usize) -> Option<(usize, usize, u32)> {
+fn find_next_gear(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], mut i: usize, mut j: usize) -> Option<(usize, usize, u64)> {
     while i < MATRIX_SIZE && j < MATRIX_SIZE {
         if let Some(gear_ratio) = is_gear_with_ratio(matrix, i, j){
             return Some((i, j, gear_ratio));
@@ -77,6 +120,7 @@ fn find_next_gear(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], mut i: usize, mut
     None
 }
 
+#[allow(clippy::is_digit_ascii_radix)]
 fn find_next_digit(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], mut i: usize, mut j: usize) -> Option<(usize, usize)> {
     while i < MATRIX_SIZE && j < MATRIX_SIZE {
         if matrix[i][j].is_digit(10) {
@@ -91,7 +135,7 @@ fn find_next_digit(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], mut i: usize, mu
     None
 }
 
-fn is_gear_with_ratio(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j: usize) -> Option<u32> {
+fn is_gear_with_ratio(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j: usize) -> Option<u64> {
     /*
     Check any position with a c:
 
@@ -106,18 +150,19 @@ fn is_gear_with_ratio(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j:
     }
 
     let mut part_numbers = 0;
-    let mut gear_ratio = 1;
+    // saturates only when more than two part numbers touch the star, which is not a gear anyway
+    let mut gear_ratio: u64 = 1;
 
     // check left of the gear
     if let Some(part_number) = connected_part_number_left(matrix, i, j) {
         part_numbers += 1;
-        gear_ratio *= part_number;
+        gear_ratio = gear_ratio.saturating_mul(part_number as u64);
     }
 
     // check right of the gear
     if let Some(part_number) = connected_part_number_right(matrix, i, j) {
         part_numbers += 1;
-        gear_ratio *= part_number;
+        gear_ratio = gear_ratio.saturating_mul(part_number as u64);
     }
 
     // check above the gear
@@ -125,16 +170,16 @@ fn is_gear_with_ratio(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j:
         match connected_part_numbers_horizontal(matrix, i-1, j) {
             (Some(left_part_number), Some(right_part_number)) => {
                 part_numbers += 2;
-                gear_ratio *= left_part_number;
-                gear_ratio *= right_part_number;
+                gear_ratio = gear_ratio.saturating_mul(left_part_number as u64);
+                gear_ratio = gear_ratio.saturating_mul(right_part_number as u64);
             },
             (Some(part_number), None) => {
                 part_numbers += 1;
-                gear_ratio *= part_number;
+                gear_ratio = gear_ratio.saturating_mul(part_number as u64);
             },
             (None, Some(part_number)) => {
                 part_numbers += 1;
-                gear_ratio *= part_number;
+                gear_ratio = gear_ratio.saturating_mul(part_number as u64);
             },
             (None, None) => (),
         }
@@ -145,16 +190,16 @@ fn is_gear_with_ratio(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j:
         match connected_part_numbers_horizontal(matrix, i+1, j) {
             (Some(left_part_number), Some(right_part_number)) => {
                 part_numbers += 2;
-                gear_ratio *= left_part_number;
-                gear_ratio *= right_part_number;
+                gear_ratio = gear_ratio.saturating_mul(left_part_number as u64);
+                gear_ratio = gear_ratio.saturating_mul(right_part_number as u64);
             },
             (Some(part_number), None) => {
                 part_numbers += 1;
-                gear_ratio *= part_number;
+                gear_ratio = gear_ratio.saturating_mul(part_number as u64);
             },
             (None, Some(part_number)) => {
                 part_numbers += 1;
-                gear_ratio *= part_number;
+                gear_ratio = gear_ratio.saturating_mul(part_number as u64);
             },
             (None, None) => (),
         }
@@ -167,6 +212,7 @@ fn is_gear_with_ratio(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j:
     }
 }
 
+#[allow(clippy::is_digit_ascii_radix)]
 fn connected_part_number_left(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j: usize) -> Option<u32> {
     if j == 0 || !matrix[i][j-1].is_digit(10) {
         return None;
@@ -184,6 +230,7 @@ fn connected_part_number_left(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: us
     Some(part_number)
 }
 
+#[allow(clippy::is_digit_ascii_radix, clippy::needless_range_loop)]
 fn connected_part_number_right(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j: usize) -> Option<u32> {
     if j == (MATRIX_SIZE - 1) || !matrix[i][j+1].is_digit(10) {
         return None;
@@ -199,6 +246,7 @@ fn connected_part_number_right(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: u
     Some(part_number)
 }
 
+#[allow(clippy::is_digit_ascii_radix, clippy::needless_return)]
 fn connected_part_numbers_horizontal(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j: usize) -> (Option<u32>, Option<u32>) {
     if i >= MATRIX_SIZE {
         return (None, None);
@@ -233,6 +281,7 @@ fn connected_part_numbers_horizontal(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE]
     );
 }
 
+#[allow(clippy::is_digit_ascii_radix, clippy::needless_range_loop)]
 fn parse_part_number(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j: usize) -> Option<u32> {
     let mut part_number = 0;
     for k in j..MATRIX_SIZE {
@@ -245,6 +294,7 @@ fn parse_part_number(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j: u
     Some(part_number)
 }
 
+#[allow(clippy::needless_range_loop)]
 fn cell_range_has_symbol_neighbor(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i: usize, j_start: usize, j_end: usize) -> bool {
     /*
     Check any position with a c:
@@ -289,10 +339,306 @@ fn cell_range_has_symbol_neighbor(matrix: &[[char; MATRIX_SIZE]; MATRIX_SIZE], i
     false
 }
 
+#[allow(clippy::match_like_matches_macro)]
 fn is_symbol(c: char) -> bool {
     match c {
         '0'..='9' => false,
         '.' => false,
         _ => true,
     }
-}
\ No newline at end of file
+}
+
+struct PartNumber {
+    j_start: usize,
+    j_end: usize,
+    value: u64,
+}
+
+struct Row {
+    cells: Vec<char>,
+    part_numbers: Vec<PartNumber>,
+}
+
+impl Row {
+    // new splits line i (0-based) into its cells and part numbers
+    fn new(i: usize, line: &str) -> Result<Self, SchematicError> {
+        let cells: Vec<char> = line.chars().collect();
+        let mut part_numbers = Vec::new();
+        let mut j = 0;
+        while j < cells.len() {
+            if !cells[j].is_ascii_digit() {
+                j += 1;
+                continue;
+            }
+            let j_start = j;
+            while j + 1 < cells.len() && cells[j+1].is_ascii_digit() {
+                j += 1;
+            }
+            let value = cells[j_start..=j]
+                .iter()
+                .collect::<String>()
+                .parse::<u64>()
+                .map_err(|_| SchematicError::NumberTooLong { row: i + 1, column: j_start + 1 })?;
+            part_numbers.push(PartNumber { j_start, j_end: j, value });
+            j += 1;
+        }
+        Ok(Row { cells, part_numbers })
+    }
+
+    // cells outside of the row behave like '.'
+    fn cell(&self, j: usize) -> char {
+        self.cells.get(j).copied().unwrap_or('.')
+    }
+
+    fn has_symbol_in(&self, j_start: usize, j_end: usize) -> bool {
+        (j_start..=j_end).any(|j| is_symbol(self.cell(j)))
+    }
+
+    fn part_numbers_touching(&self, j: usize) -> impl Iterator<Item = u64> + '_ {
+        self.part_numbers
+            .iter()
+            .filter(move |part_number| part_number.j_start <= j + 1 && j <= part_number.j_end + 1)
+            .map(|part_number| part_number.value)
+    }
+}
+
+// scan_rows computes the sum of part numbers and the sum of gear ratios in a single pass.
+// Only the rows above and below the current row are kept in memory.
+fn scan_rows(lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<(u64, u64), Box<dyn std::error::Error>> {
+    let mut sum_part_numbers: u64 = 0;
+    let mut sum_gear_ratios: u64 = 0;
+    // window[0] is above the current row, window[1] is the current row and window[2] is below it
+    let mut window: [Option<Row>; 3] = [None, None, None];
+    // a trailing None shifts the last row into the current position
+    for (i, line) in lines.map(Some).chain(std::iter::once(None)).enumerate() {
+        window.rotate_left(1);
+        window[2] = match line {
+            Some(line) => Some(Row::new(i, &line?)?),
+            None => None,
+        };
+        if let Some(current) = &window[1] {
+            // line i (0-based) is below the current row, so the current row is row i (1-based)
+            let (part_numbers, gear_ratios) = scan_row(window[0].as_ref(), current, window[2].as_ref()).ok_or(SchematicError::Overflow { row: i })?;
+            sum_part_numbers = sum_part_numbers.checked_add(part_numbers).ok_or(SchematicError::Overflow { row: i })?;
+            sum_gear_ratios = sum_gear_ratios.checked_add(gear_ratios).ok_or(SchematicError::Overflow { row: i })?;
+        }
+    }
+    Ok((sum_part_numbers, sum_gear_ratios))
+}
+
+// scan_row returns None if one of the sums overflows
+fn scan_row(above: Option<&Row>, current: &Row, below: Option<&Row>) -> Option<(u64, u64)> {
+    let neighbors = [above, Some(current), below];
+
+    let mut sum_part_numbers: u64 = 0;
+    for part_number in &current.part_numbers {
+        let j_start = part_number.j_start.saturating_sub(1);
+        let j_end = part_number.j_end + 1;
+        if neighbors.iter().flatten().any(|row| row.has_symbol_in(j_start, j_end)) {
+            sum_part_numbers = sum_part_numbers.checked_add(part_number.value)?;
+        }
+    }
+
+    let mut sum_gear_ratios: u64 = 0;
+    for (j, c) in current.cells.iter().enumerate() {
+        if *c != '*' {
+            continue;
+        }
+        let part_numbers: Vec<u64> = neighbors.iter().flatten().flat_map(|row| row.part_numbers_touching(j)).collect();
+        if part_numbers.len() == 2 {
+            sum_gear_ratios = sum_gear_ratios.checked_add(part_numbers[0].checked_mul(part_numbers[1])?)?;
+        }
+    }
+
+    Some((sum_part_numbers, sum_gear_ratios))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"467..114..
+...*......
+..35..633.
+......#...
+617*......
+.....+.58.
+..592.....
+......755.
+...$.*....
+.664.598.."#;
+
+    fn lines(input: &str) -> impl Iterator<Item = Result<String, io::Error>> + '_ {
+        input.lines().map(|s| Ok(s.to_string()))
+    }
+
+    fn scan_matrix(input: &str) -> (u64, u64) {
+        let matrix = read_matrix(lines(input)).unwrap();
+        (find_all_numbers(&matrix), find_gears(&matrix))
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(scan_matrix(EXAMPLE), (4361, 467835));
+        assert_eq!(scan_rows(lines(EXAMPLE)).unwrap(), (4361, 467835));
+    }
+
+    // read_matrix used to pad with '\0', which is_symbol counts as a symbol, so every number on the
+    // right or bottom edge of a schematic smaller than 140x140 was counted as a part number.
+    #[test]
+    fn test_padding_is_not_a_symbol() {
+        assert!(is_symbol('\0'));
+        assert_eq!(scan_matrix("12"), (0, 0));
+        assert_eq!(scan_matrix("..\n.5"), (0, 0));
+        assert_eq!(scan_matrix("7*\n.3"), (10, 21));
+    }
+
+    #[test]
+    fn test_streaming_matches_matrix() {
+        let input = include_str!("input.txt");
+        assert_eq!(scan_rows(lines(input)).unwrap(), scan_matrix(input));
+    }
+
+    #[test]
+    fn test_streaming_edges() {
+        assert_eq!(scan_rows(lines("")).unwrap(), (0, 0));
+        assert_eq!(scan_rows(lines("12*3")).unwrap(), (15, 36));
+        assert_eq!(scan_rows(lines("1.\n*\n2")).unwrap(), (3, 2));
+        assert_eq!(scan_rows(lines("..7\n.\n..*")).unwrap(), (0, 0));
+    }
+
+    // gear_ratios keeps its own copy of the xorshift generator, every run checks the same schematics.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    const SYMBOLS: [char; 8] = ['*', '#', '+', '$', '/', '@', '=', '-'];
+
+    // random_schematic fills a grid of up to max_rows x max_columns with dots, numbers of up to three digits and symbols,
+    // gears are frequent
+    fn random_schematic(rng: &mut Rng, max_rows: usize, max_columns: usize) -> Vec<Vec<char>> {
+        let (rows, columns) = (1 + rng.below(max_rows), 1 + rng.below(max_columns));
+        let mut grid = vec![vec!['.'; columns]; rows];
+        for row in grid.iter_mut() {
+            let mut j = 0;
+            while j < columns {
+                match rng.below(6) {
+                    0 | 1 => {
+                        for _ in 0..1 + rng.below(3) {
+                            if j < columns {
+                                row[j] = char::from(b'0' + rng.below(10) as u8);
+                                j += 1;
+                            }
+                        }
+                    }
+                    2 => row[j] = if rng.below(2) == 0 { '*' } else { SYMBOLS[rng.below(SYMBOLS.len())] },
+                    _ => {}
+                }
+                j += 1;
+            }
+        }
+        grid
+    }
+
+    fn to_text(grid: &[Vec<char>]) -> String {
+        let rows: Vec<String> = grid.iter().map(|row| row.iter().collect()).collect();
+        rows.join("\n")
+    }
+
+    // reference looks at every number and every '*' on its own, without any of the scanning shortcuts
+    fn reference(grid: &[Vec<char>]) -> (u64, u64) {
+        let mut numbers = Vec::new();
+        for (i, row) in grid.iter().enumerate() {
+            let mut j = 0;
+            while j < row.len() {
+                if row[j].is_ascii_digit() {
+                    let start = j;
+                    while j < row.len() && row[j].is_ascii_digit() {
+                        j += 1;
+                    }
+                    let value: u64 = row[start..j].iter().collect::<String>().parse().unwrap();
+                    numbers.push((i, start, j - 1, value));
+                } else {
+                    j += 1;
+                }
+            }
+        }
+        let touches = |(i, start, end, _): (usize, usize, usize, u64), (y, x): (usize, usize)| {
+            y + 1 >= i && y <= i + 1 && x + 1 >= start && x <= end + 1
+        };
+        let mut part_sum = 0;
+        for number in &numbers {
+            let mut neighbors = (number.0.saturating_sub(1)..=number.0 + 1).flat_map(|y| (number.1.saturating_sub(1)..=number.2 + 1).map(move |x| (y, x)));
+            if neighbors.any(|(y, x)| grid.get(y).and_then(|row| row.get(x)).is_some_and(|c| is_symbol(*c))) {
+                part_sum += number.3;
+            }
+        }
+        let mut gear_sum = 0;
+        for (y, row) in grid.iter().enumerate() {
+            for (x, _) in row.iter().enumerate().filter(|(_, c)| **c == '*') {
+                let adjacent: Vec<u64> = numbers.iter().filter(|number| touches(**number, (y, x))).map(|number| number.3).collect();
+                if adjacent.len() == 2 {
+                    gear_sum += adjacent[0] * adjacent[1];
+                }
+            }
+        }
+        (part_sum, gear_sum)
+    }
+
+    #[test]
+    fn test_random_schematics_match_reference() {
+        let mut rng = Rng(0x6ea5);
+        for _ in 0..500 {
+            let grid = random_schematic(&mut rng, 12, 20);
+            let text = to_text(&grid);
+            let expected = reference(&grid);
+            assert_eq!(scan_rows(lines(&text)).unwrap(), expected, "\n{}", text);
+            assert_eq!(scan_matrix(&text), expected, "\n{}", text);
+        }
+    }
+
+    #[test]
+    fn test_adding_a_symbol_never_decreases_the_sum() {
+        let mut rng = Rng(0x5e7);
+        for _ in 0..300 {
+            let mut grid = random_schematic(&mut rng, 10, 16);
+            let (mut part_sum, _) = scan_rows(lines(&to_text(&grid))).unwrap();
+            for _ in 0..10 {
+                let (i, j) = (rng.below(grid.len()), rng.below(grid[0].len()));
+                if grid[i][j] != '.' {
+                    continue;
+                }
+                grid[i][j] = SYMBOLS[rng.below(SYMBOLS.len())];
+                let (new_part_sum, _) = scan_rows(lines(&to_text(&grid))).unwrap();
+                assert!(new_part_sum >= part_sum, "\n{}", to_text(&grid));
+                part_sum = new_part_sum;
+            }
+        }
+    }
+
+    #[test]
+    fn test_invalid_schematics() {
+        let error = |input: &str| read_matrix(lines(input)).unwrap_err().to_string();
+        assert_eq!(error(&".".repeat(141)), "row 1, column 141: schematic is larger than 140x140 (use --stream)");
+        assert_eq!(error(&".\n".repeat(141)), "row 141, column 1: schematic is larger than 140x140 (use --stream)");
+        assert_eq!(error("..1234567*"), "row 1, column 3: part number is too long, the limit is 6 digits or 18446744073709551615 with --stream");
+        assert_eq!(scan_matrix("617617*\n.644.597"), (617617 + 597, 617617 * 597));
+        assert_eq!(scan_matrix(&"123456*123456.".repeat(10)), (20 * 123456, 10 * 123456 * 123456));
+
+        let error = scan_rows(lines(&format!("{}*\n{}", u64::MAX, u64::MAX))).unwrap_err();
+        assert_eq!(error.to_string(), "row 1: sum overflows");
+        let error = scan_rows(lines(&format!("4294967296*4294967296\n.\n{}\n*", u64::MAX))).unwrap_err();
+        assert_eq!(error.to_string(), "row 1: sum overflows");
+
+        let error = scan_rows(lines(&format!("..\n.*{}0", u64::MAX))).unwrap_err();
+        assert_eq!(error.to_string(), "row 2, column 3: part number is too long, the limit is 6 digits or 18446744073709551615 with --stream");
+        assert_eq!(scan_rows(lines(&format!("{}*", u64::MAX))).unwrap(), (u64::MAX, 0));
+    }
+}
@@ -1,79 +1,602 @@
-use std::{collections::{HashSet, HashMap}, io::{self, BufRead}};
+use std::{collections::HashSet, io::{self, BufRead}};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let stdin = std::io::stdin();
     let lines = stdin.lock().lines();
 
-    let (sum_part_1, sum_part_2) = calculate_wins(lines)?;
+    let args: Vec<String> = std::env::args().collect();
+    // --unordered accepts decks with shuffled card ids or gaps between them
+    let order = if args.iter().any(|arg| arg == "--unordered") { DeckOrder::Unordered } else { DeckOrder::Strict };
+    if args.iter().any(|arg| arg == "--explain") {
+        let (traces, overreaches) = trace_cascade(&read_deck(lines, order)?)?;
+        for trace in traces {
+            println!("{}", trace);
+        }
+        report_overreaches(&overreaches);
+        return Ok(());
+    }
+    if args.iter().any(|arg| arg == "--dot") {
+        let (traces, overreaches) = trace_cascade(&read_deck(lines, order)?)?;
+        print!("{}", cascade_to_dot(&traces));
+        report_overreaches(&overreaches);
+        return Ok(());
+    }
+
+    let (sum_part_1, sum_part_2, overreaches) = match order {
+        DeckOrder::Strict => calculate_wins(lines)?,
+        DeckOrder::Unordered => calculate_wins_unordered(lines)?,
+    };
 
     println!("part 1: {}", sum_part_1);
     println!("part 2: {}", sum_part_2);
+    report_overreaches(&overreaches);
 
     Ok(())
 }
 
-fn calculate_wins(lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<(u32, usize), Box<dyn std::error::Error>> {
-    let mut card_counts: HashMap<usize, usize> = HashMap::new();
-    let mut sum_part_1: u32 = 0;
+fn report_overreaches(overreaches: &[Overreach]) {
+    for overreach in overreaches {
+        eprintln!("warning: {}", overreach);
+    }
+}
+
+// calculate_wins streams a deck whose card ids count up from 1 without gaps.
+fn calculate_wins(lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<(u64, u128, Vec<Overreach>), Box<dyn std::error::Error>> {
+    let mut copy_counts = CopyCounts::new();
+    let mut sum_part_1: u64 = 0;
+    let mut sum_part_2: u128 = 0;
+    // cards whose wins may still point past the last card, the others can not overreach anymore
+    let mut reaching: Vec<Card> = Vec::new();
+    let mut last_card = 0;
     for (i, line) in lines.enumerate() {
-        let line = line?;
-        let (winning_numbers, have_numbers) = parse_line(&line).ok_or("invalid line")?;
+        let card = read_card(i, &line?)?;
+        check_strict_order(i, &card)?;
+        let matches = card.matches;
+        last_card = card.id;
+        reaching.retain(|card| card.id + card.matches > last_card);
+        if matches > 0 {
+            reaching.push(card);
+        }
         // part 1
-        sum_part_1 += part_1(&winning_numbers, &have_numbers);
+        sum_part_1 = part_1(matches)
+            .and_then(|points| sum_part_1.checked_add(points))
+            .ok_or(format!("line {}: points overflow", i + 1))?;
         // part 2
-        let count = card_counts.get(&i).unwrap_or(&0).clone() + 1;
-        card_counts.insert(i, count);
-        part_2(i, &winning_numbers, &have_numbers, &mut card_counts);
+        sum_part_2 = part_2(matches, &mut copy_counts)
+            .and_then(|instances| sum_part_2.checked_add(instances))
+            .ok_or(format!("line {}: card count overflow", i + 1))?;
+    }
+    // the card ids of a strict deck are exactly 1 to last_card
+    let overreaches = reaching.iter().filter_map(|card| overreach(card, |id| id <= last_card)).collect();
+    Ok((sum_part_1, sum_part_2, overreaches))
+}
+
+// calculate_wins_unordered reads the whole deck first, so card ids may come in any order and may have gaps.
+fn calculate_wins_unordered(lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<(u64, u128, Vec<Overreach>), Box<dyn std::error::Error>> {
+    let deck = read_deck(lines, DeckOrder::Unordered)?;
+    let mut sum_part_1: u64 = 0;
+    for card in &deck {
+        sum_part_1 = part_1(card.matches)
+            .and_then(|points| sum_part_1.checked_add(points))
+            .ok_or(format!("card {}: points overflow", card.id))?;
+    }
+    let (traces, overreaches) = trace_cascade(&deck)?;
+    let sum_part_2 = traces.last().map_or(0, |trace| trace.running_total);
+    Ok((sum_part_1, sum_part_2, overreaches))
+}
+
+struct Card {
+    id: usize,
+    matches: usize,
+}
+
+#[derive(Clone, Copy)]
+enum DeckOrder {
+    // card ids have to count up from 1 without gaps
+    Strict,
+    // card ids may come in any order and may have gaps, but must be unique
+    Unordered,
+}
+
+// read_card parses line i (0-based) into its card id and amount of matching numbers.
+// The ids of the cards a card wins copies of have to fit into a usize.
+fn read_card(i: usize, line: &str) -> Result<Card, Box<dyn std::error::Error>> {
+    let (id, winning_numbers, have_numbers) = parse_line(line).map_err(|e| format!("line {}: {}", i + 1, e))?;
+    let matches = winning_numbers.matches(&have_numbers);
+    if id.checked_add(matches).is_none() {
+        return Err(format!("line {}: card {} wins copies of cards past {}", i + 1, id, usize::MAX).into());
+    }
+    Ok(Card { id, matches })
+}
+
+fn check_strict_order(i: usize, card: &Card) -> Result<(), Box<dyn std::error::Error>> {
+    if card.id != i + 1 {
+        return Err(format!("line {}: expected card {}, found card {} (use --unordered for shuffled decks)", i + 1, i + 1, card.id).into());
+    }
+    Ok(())
+}
+
+// read_deck returns the validated deck sorted by card id.
+fn read_deck(lines: impl Iterator<Item = Result<String, io::Error>>, order: DeckOrder) -> Result<Vec<Card>, Box<dyn std::error::Error>> {
+    let mut lines_by_id = std::collections::BTreeMap::new();
+    for (i, line) in lines.enumerate() {
+        let card = read_card(i, &line?)?;
+        if let DeckOrder::Strict = order {
+            check_strict_order(i, &card)?;
+        }
+        let id = card.id;
+        if let Some((first_line, _)) = lines_by_id.insert(id, (i, card)) {
+            return Err(format!("line {}: duplicate card {} (first seen in line {})", i + 1, id, first_line + 1).into());
+        }
+    }
+    Ok(lines_by_id.into_values().map(|(_, card)| card).collect())
+}
+
+// Overreach records a card whose wins point at cards that are not part of the deck.
+#[derive(Debug, PartialEq)]
+struct Overreach {
+    card: usize,
+    missing: Vec<usize>,
+}
+
+impl std::fmt::Display for Overreach {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let missing: Vec<String> = self.missing.iter().map(|card| card.to_string()).collect();
+        write!(f, "card {} won copies of missing cards [{}]", self.card, missing.join(", "))
+    }
+}
+
+// overreach returns the cards that card wins copies of but that are not part of the deck, in_deck tells whether
+// a card id is part of it. Both deck orders report overreaches through here, so they can not drift apart.
+fn overreach(card: &Card, in_deck: impl Fn(usize) -> bool) -> Option<Overreach> {
+    let missing: Vec<usize> = (card.id+1..=card.id+card.matches).filter(|id| !in_deck(*id)).collect();
+    if missing.is_empty() {
+        return None;
+    }
+    Some(Overreach { card: card.id, missing })
+}
+
+fn part_1(matches: usize) -> Option<u64> {
+    if matches == 0 {
+        return Some(0);
+    }
+    1u64.checked_shl(u32::try_from(matches - 1).ok()?)
+}
+
+// part_2 returns the amount of instances of the current card and hands out the copies it wins.
+fn part_2(matches: usize, copy_counts: &mut CopyCounts) -> Option<u128> {
+    let instances = copy_counts.take()?;
+    copy_counts.add(matches, instances)?;
+    Some(instances)
+}
+
+// CopyCounts is a ring buffer of the copies won for upcoming cards.
+// The front belongs to the current card, so the buffer never grows beyond the largest amount of matches of a single card.
+struct CopyCounts {
+    pending: std::collections::VecDeque<u128>,
+}
+
+impl CopyCounts {
+    fn new() -> Self {
+        Self { pending: std::collections::VecDeque::new() }
+    }
+
+    // take returns the instances (original plus won copies) of the current card and advances to the next card.
+    fn take(&mut self) -> Option<u128> {
+        self.pending.pop_front().unwrap_or(0).checked_add(1)
+    }
+
+    // add hands out one copy per instance to each of the next `matches` cards.
+    fn add(&mut self, matches: usize, instances: u128) -> Option<()> {
+        if self.pending.len() < matches {
+            self.pending.resize(matches, 0);
+        }
+        for count in self.pending.iter_mut().take(matches) {
+            *count = count.checked_add(instances)?;
+        }
+        Some(())
+    }
+}
+
+// numbers below BITSET_LIMIT are stored in a bitset, larger ones fall back to a hash set
+const BITSET_LIMIT: u64 = 1 << 12;
+
+#[derive(Debug, PartialEq)]
+enum NumberSet {
+    Bits(Vec<u64>),
+    Hashed(HashSet<u64>),
+}
+
+impl NumberSet {
+    fn new(numbers: &[u64]) -> Self {
+        match numbers.iter().max() {
+            Some(max) if *max >= BITSET_LIMIT => NumberSet::Hashed(numbers.iter().copied().collect()),
+            max => {
+                let mut bits = vec![0u64; max.map_or(0, |max| *max as usize / 64 + 1)];
+                for number in numbers {
+                    bits[*number as usize / 64] |= 1 << (number % 64);
+                }
+                NumberSet::Bits(bits)
+            }
+        }
+    }
+
+    fn contains(&self, number: u64) -> bool {
+        match self {
+            NumberSet::Bits(bits) => bits.get(number as usize / 64).is_some_and(|word| word & (1 << (number % 64)) != 0),
+            NumberSet::Hashed(numbers) => numbers.contains(&number),
+        }
+    }
+
+    // matches counts the numbers contained in both sets
+    fn matches(&self, other: &NumberSet) -> usize {
+        match (self, other) {
+            (NumberSet::Bits(a), NumberSet::Bits(b)) => a.iter().zip(b).map(|(a, b)| (a & b).count_ones() as usize).sum(),
+            (NumberSet::Hashed(a), NumberSet::Hashed(b)) => a.intersection(b).count(),
+            (NumberSet::Hashed(a), b) | (b, NumberSet::Hashed(a)) => a.iter().filter(|number| b.contains(**number)).count(),
+        }
+    }
+}
+
+// CardTrace explains how the copies of a single card came about in part 2.
+struct CardTrace {
+    card: usize,
+    matches: usize,
+    copies: u128,
+    // cards that received copies from this card
+    fed: Vec<usize>,
+    // total amount of cards including all cards up to this one
+    running_total: u128,
+}
+
+impl std::fmt::Display for CardTrace {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let fed: Vec<String> = self.fed.iter().map(|card| card.to_string()).collect();
+        write!(
+            f,
+            "card {}: {} matches, {} copies, fed [{}], running total {}",
+            self.card, self.matches, self.copies, fed.join(", "), self.running_total,
+        )
     }
-    Ok((
-        sum_part_1,
-        card_counts.into_iter().fold(0, |acc, (_, count)| acc + count)
-    ))
 }
 
-fn part_1(winning_numbers: &HashSet<u32>, have_numbers: &HashSet<u32>) -> u32 {
-    let count = winning_numbers.intersection(have_numbers).count();
-    (1 << count as u32) >> 1
+// trace_cascade expects the deck to be sorted by card id.
+fn trace_cascade(deck: &[Card]) -> Result<(Vec<CardTrace>, Vec<Overreach>), Box<dyn std::error::Error>> {
+    let mut traces: Vec<CardTrace> = deck
+        .iter()
+        .map(|card| CardTrace { card: card.id, matches: card.matches, copies: 1, fed: Vec::new(), running_total: 0 })
+        .collect();
+    let mut overreaches = Vec::new();
+    let mut running_total: u128 = 0;
+    for i in 0..traces.len() {
+        let copies = traces[i].copies;
+        for target in traces[i].card+1..=traces[i].card+traces[i].matches {
+            if let Ok(j) = traces.binary_search_by_key(&target, |trace| trace.card) {
+                traces[j].copies = traces[j].copies.checked_add(copies).ok_or(format!("card {}: card count overflow", target))?;
+                traces[i].fed.push(target);
+            }
+        }
+        overreaches.extend(overreach(&deck[i], |id| deck.binary_search_by_key(&id, |card| card.id).is_ok()));
+        running_total = running_total.checked_add(copies).ok_or(format!("card {}: card count overflow", traces[i].card))?;
+        traces[i].running_total = running_total;
+    }
+    Ok((traces, overreaches))
 }
 
-fn part_2(i: usize, winning_numbers: &HashSet<u32>, have_numbers: &HashSet<u32>, card_counts: &mut HashMap<usize, usize>) {
-    // instances is the amount of same cards we have collected
-    let instances = card_counts.get(&i).unwrap_or(&0).clone();
-    // wins is the amount of numbers that match
-    let wins = winning_numbers.intersection(have_numbers).count();
-    for j in i+1..i+1+wins {
-        match card_counts.get(&j) {
-            Some(count) => { card_counts.insert(j, count + instances); }
-            None => { card_counts.insert(j, instances); }
+// cascade_to_dot renders the cascade as a graphviz digraph.
+// Every edge is labeled with the amount of copies that were passed along.
+fn cascade_to_dot(traces: &[CardTrace]) -> String {
+    let mut dot = String::from("digraph cascade {\n");
+    for trace in traces {
+        dot += &format!(
+            "    card{} [label=\"Card {}\\n{} matches\\n{} copies\"];\n",
+            trace.card, trace.card, trace.matches, trace.copies,
+        );
+    }
+    for trace in traces {
+        for fed in &trace.fed {
+            dot += &format!("    card{} -> card{} [label=\"{}\"];\n", trace.card, fed, trace.copies);
         }
     }
+    dot += "}\n";
+    dot
 }
 
-fn parse_line(line: &str) -> Option<(HashSet<u32>, HashSet<u32>)> {
-    let line = line.split_once(':')?;
-    let parts = line.1.split_once("|")?;
-    let winning_part = parts.0.trim_end();
-    let have_part = parts.1.trim_end();
-    Some((parse_number_grid(winning_part), parse_number_grid(have_part)))
-}   
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    // 1-based column in the line
+    column: usize,
+    message: String,
+}
 
-fn parse_number_grid(grid: &str) -> HashSet<u32> {
-    let mut numbers = HashSet::new();
-    let safe_gridlen = grid.len() - (grid.len() % 3);
-    for i in (0..safe_gridlen).step_by(3) {
-        let mut number = 0;
-        for j in 0..3 {
-            number = (number * 10) + read_digit(grid.chars().nth(i + j).unwrap());
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "column {}: {}", self.column, self.message)
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn parse_line(line: &str) -> Result<(usize, NumberSet, NumberSet), ParseError> {
+    let colon = line.find(':').ok_or(ParseError { column: 1, message: "expected ':' after card id".to_string() })?;
+    let id = parse_card_id(line, colon)?;
+    let bar = line[colon..].find('|').map(|i| colon + i).ok_or(ParseError {
+        column: line.chars().count() + 1,
+        message: "expected '|' between winning and have numbers".to_string(),
+    })?;
+    let winning_numbers = parse_number_list(line, colon + 1, bar)?;
+    let have_numbers = parse_number_list(line, bar + 1, line.len())?;
+    Ok((id, NumberSet::new(&winning_numbers), NumberSet::new(&have_numbers)))
+}
+
+// parse_card_id reads the "Card N" prefix in line[..colon].
+fn parse_card_id(line: &str, colon: usize) -> Result<usize, ParseError> {
+    let rest = line[..colon].strip_prefix("Card").ok_or(ParseError { column: 1, message: "expected 'Card'".to_string() })?;
+    let id_start = colon - rest.trim_start().len();
+    if id_start == colon {
+        return Err(ParseError { column: line[..colon].chars().count() + 1, message: "expected card id".to_string() });
+    }
+    if id_start == "Card".len() {
+        return Err(ParseError { column: id_start + 1, message: "expected whitespace after 'Card'".to_string() });
+    }
+    let id = parse_number(line, id_start, colon)?;
+    usize::try_from(id).map_err(|_| ParseError { column: id_start + 1, message: format!("card id {} is out of range", id) })
+}
+
+// parse_number_list reads the whitespace separated numbers in line[start..end].
+// Numbers may have any amount of digits and may be separated by any whitespace.
+fn parse_number_list(line: &str, start: usize, end: usize) -> Result<Vec<u64>, ParseError> {
+    let mut numbers = Vec::new();
+    let mut token_start: Option<usize> = None;
+    for (i, c) in line[start..end].char_indices().map(|(i, c)| (start + i, c)).chain(std::iter::once((end, ' '))) {
+        match (c.is_whitespace(), token_start) {
+            (true, Some(token_start_index)) => {
+                numbers.push(parse_number(line, token_start_index, i)?);
+                token_start = None;
+            }
+            (false, None) => token_start = Some(i),
+            _ => (),
         }
-        numbers.insert(number);
     }
+    Ok(numbers)
+}
 
-    numbers
+fn parse_number(line: &str, start: usize, end: usize) -> Result<u64, ParseError> {
+    let token = &line[start..end];
+    let column = line[..start].chars().count() + 1;
+    if let Some((offset, c)) = token.char_indices().find(|(_, c)| !c.is_ascii_digit()) {
+        return Err(ParseError {
+            column: column + token[..offset].chars().count(),
+            message: format!("invalid character {:?} in number {:?}", c, token),
+        });
+    }
+    token.parse::<u64>().map_err(|_| ParseError { column, message: format!("number {} is out of range", token) })
 }
 
-fn read_digit(c: char) -> u32 {
-    match c {
-        '0'..='9' => c as u32 - '0' as u32,
-        _ => 0,
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = r#"Card 1: 41 48 83 86 17 | 83 86  6 31 17  9 48 53
+Card 2: 13 32 20 16 61 | 61 30 68 82 17 32 24 19
+Card 3:  1 21 53 59 44 | 69 82 63 72 16 21 14  1
+Card 4: 41 92 73 84 69 | 59 84 76 51 58  5 54 83
+Card 5: 87 83 26 28 32 | 88 30 70 12 93 22 82 36
+Card 6: 31 18 13 56 72 | 74 77 10 23 35 67 36 11"#;
+
+    fn lines(input: &str) -> impl Iterator<Item = Result<String, io::Error>> + '_ {
+        input.lines().map(|s| Ok(s.to_string()))
+    }
+
+    #[test]
+    fn test_calculate_wins() {
+        assert_eq!(calculate_wins(lines(EXAMPLE)).unwrap(), (13, 30, vec![]));
+        assert_eq!(calculate_wins_unordered(lines(EXAMPLE)).unwrap(), (13, 30, vec![]));
+    }
+
+    #[test]
+    fn test_card_order() {
+        let shuffled: Vec<&str> = EXAMPLE.lines().rev().collect();
+        let shuffled = shuffled.join("\n");
+        let err = calculate_wins(lines(&shuffled)).unwrap_err();
+        assert_eq!(err.to_string(), "line 1: expected card 1, found card 6 (use --unordered for shuffled decks)");
+        assert_eq!(calculate_wins_unordered(lines(&shuffled)).unwrap(), (13, 30, vec![]));
+
+        let duplicate = format!("{}\nCard 3: 1 | 2", EXAMPLE);
+        let err = calculate_wins_unordered(lines(&duplicate)).unwrap_err();
+        assert_eq!(err.to_string(), "line 7: duplicate card 3 (first seen in line 3)");
+    }
+
+    #[test]
+    fn test_overreach() {
+        let deck = "Card 1: 1 2 | 1 2\nCard 2: 1 2 3 | 1 2 3";
+        let overreaches = vec![
+            Overreach { card: 1, missing: vec![3] },
+            Overreach { card: 2, missing: vec![3, 4, 5] },
+        ];
+        assert_eq!(calculate_wins(lines(deck)).unwrap(), (6, 3, overreaches));
+        assert_eq!(calculate_wins_unordered(lines(deck)).unwrap().2[1].to_string(), "card 2 won copies of missing cards [3, 4, 5]");
+
+        let gaps = "Card 4: 1 | 2\nCard 1: 1 2 | 1 2\nCard 3: 1 | 1";
+        let overreaches = vec![Overreach { card: 1, missing: vec![2] }];
+        assert_eq!(calculate_wins_unordered(lines(gaps)).unwrap(), (3, 6, overreaches));
+    }
+
+    #[test]
+    fn test_number_set() {
+        let small = NumberSet::new(&[1, 64, 65, 4095]);
+        let large = NumberSet::new(&[64, 4095, 4096, 1 << 40]);
+        assert!(matches!(small, NumberSet::Bits(_)));
+        assert!(matches!(large, NumberSet::Hashed(_)));
+        assert!(small.contains(64));
+        assert!(!small.contains(63));
+        assert!(!small.contains(1 << 20));
+        assert_eq!(small.matches(&small), 4);
+        assert_eq!(small.matches(&large), 2);
+        assert_eq!(large.matches(&small), 2);
+        assert_eq!(large.matches(&large), 4);
+        assert_eq!(small.matches(&NumberSet::new(&[1])), 1);
+    }
+
+    #[test]
+    fn test_copy_counts_overflow() {
+        assert_eq!(part_1(1), Some(1));
+        assert_eq!(part_1(64), Some(1 << 63));
+        assert_eq!(part_1(65), None);
+        let mut copy_counts = CopyCounts::new();
+        assert_eq!(part_2(3, &mut copy_counts), Some(1));
+        assert_eq!(copy_counts.pending.len(), 3);
+        copy_counts.pending[0] = u128::MAX - 1;
+        copy_counts.pending[1] = u128::MAX;
+        assert_eq!(part_2(0, &mut copy_counts), Some(u128::MAX));
+        assert_eq!(part_2(0, &mut copy_counts), None);
     }
-}
\ No newline at end of file
+
+    #[test]
+    fn test_card_ids_up_to_usize_max() {
+        let deck = format!("Card {}: 1 2 | 1 2", usize::MAX - 2);
+        let (traces, overreaches) = trace_cascade(&read_deck(lines(&deck), DeckOrder::Unordered).unwrap()).unwrap();
+        assert_eq!(traces.len(), 1);
+        assert_eq!(overreaches, [Overreach { card: usize::MAX - 2, missing: vec![usize::MAX - 1, usize::MAX] }]);
+
+        let deck = format!("Card 1: 1 | 1\nCard {}: 1 2 | 1 2", usize::MAX - 1);
+        let error = calculate_wins_unordered(lines(&deck)).unwrap_err();
+        assert_eq!(error.to_string(), format!("line 2: card {} wins copies of cards past {}", usize::MAX - 1, usize::MAX));
+    }
+
+    #[test]
+    fn test_trace_cascade() {
+        let (traces, overreaches) = trace_cascade(&read_deck(lines(EXAMPLE), DeckOrder::Strict).unwrap()).unwrap();
+        assert!(overreaches.is_empty());
+        let copies: Vec<u128> = traces.iter().map(|trace| trace.copies).collect();
+        assert_eq!(copies, vec![1, 2, 4, 8, 14, 1]);
+        assert_eq!(traces[0].fed, vec![2, 3, 4, 5]);
+        assert_eq!(traces[3].fed, vec![5]);
+        assert!(traces[5].fed.is_empty());
+        assert_eq!(traces[5].running_total, 30);
+        assert_eq!(traces[1].to_string(), "card 2: 2 matches, 2 copies, fed [3, 4], running total 3");
+    }
+
+    #[test]
+    fn test_cascade_to_dot() {
+        let dot = cascade_to_dot(&trace_cascade(&read_deck(lines(EXAMPLE), DeckOrder::Strict).unwrap()).unwrap().0);
+        assert!(dot.starts_with("digraph cascade {\n"));
+        assert!(dot.contains("    card5 [label=\"Card 5\\n0 matches\\n14 copies\"];\n"));
+        assert!(dot.contains("    card3 -> card5 [label=\"4\"];\n"));
+        assert_eq!(dot.matches("->").count(), 9);
+    }
+
+    #[test]
+    fn test_parse_line() {
+        let (id, winning, have) = parse_line("Card 1: 1234 5\t6 |7  89012\t").unwrap();
+        assert_eq!(id, 1);
+        assert_eq!(winning, NumberSet::new(&[1234, 5, 6]));
+        assert_eq!(have, NumberSet::Hashed(HashSet::from([7, 89012])));
+        let (id, winning, have) = parse_line("Card\t 12345:|").unwrap();
+        assert_eq!(id, 12345);
+        assert_eq!(winning, NumberSet::Bits(vec![]));
+        assert_eq!(have, NumberSet::Bits(vec![]));
+        assert_eq!(parse_number_list(" 1 22\t333  4444 ", 0, 16).unwrap(), vec![1, 22, 333, 4444]);
+    }
+
+    #[test]
+    fn test_parse_line_errors() {
+        assert_eq!(parse_line("Card 1 41 | 83").unwrap_err().column, 1);
+        assert_eq!(parse_line("Crd 1: 41 | 83").unwrap_err().column, 1);
+        assert_eq!(parse_line("Card1: 41 | 83").unwrap_err().column, 5);
+        assert_eq!(parse_line("Card : 41 | 83").unwrap_err().column, 6);
+        assert_eq!(parse_line("Card 1a: 41 | 83").unwrap_err().column, 7);
+        assert_eq!(parse_line("Card 1: 41 83").unwrap_err().column, 14);
+        let err = parse_line("Card 1: 41 4x2 | 83").unwrap_err();
+        assert_eq!(err.column, 13);
+        assert_eq!(err.to_string(), "column 13: invalid character 'x' in number \"4x2\"");
+        assert_eq!(parse_line("Card 1: 41 | 83 -5").unwrap_err().column, 17);
+        assert_eq!(parse_line("Card 1: 41 | 99999999999999999999").unwrap_err().column, 14);
+    }
+
+    // A local xorshift generator, the days do not depend on each other. A fixed seed gives the same decks every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: usize) -> usize {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 % n as u64) as usize
+        }
+    }
+
+    // distinct_numbers draws fewer than max_count different numbers below 100
+    fn distinct_numbers(rng: &mut Rng, max_count: usize) -> Vec<u64> {
+        let count = rng.below(max_count);
+        let mut numbers = Vec::new();
+        while numbers.len() < count {
+            let number = rng.below(100) as u64;
+            if !numbers.contains(&number) {
+                numbers.push(number);
+            }
+        }
+        numbers
+    }
+
+    // random_deck writes a deck of cards numbered from 1 and returns it with the matches of every card
+    fn random_deck(rng: &mut Rng, cards: usize) -> (Vec<String>, Vec<usize>) {
+        let mut lines = Vec::new();
+        let mut matches = Vec::new();
+        for id in 1..=cards {
+            let winning = distinct_numbers(rng, 8);
+            let have = distinct_numbers(rng, 20);
+            matches.push(have.iter().filter(|number| winning.contains(number)).count());
+            let list = |numbers: &[u64]| numbers.iter().map(|number| format!("{:2}", number)).collect::<Vec<_>>().join(" ");
+            lines.push(format!("Card {:3}: {} | {}", id, list(&winning), list(&have)));
+        }
+        (lines, matches)
+    }
+
+    // reference plays the deck card by card, copies of cards past the end are dropped
+    fn reference(matches: &[usize]) -> (u64, u128, Vec<u128>) {
+        let points = matches.iter().filter(|m| **m > 0).map(|m| 1 << (m - 1)).sum();
+        let mut copies = vec![1u128; matches.len()];
+        for (i, m) in matches.iter().enumerate() {
+            for j in i + 1..std::cmp::min(i + 1 + m, matches.len()) {
+                copies[j] += copies[i];
+            }
+        }
+        (points, copies.iter().sum(), copies)
+    }
+
+    #[test]
+    fn test_random_decks_match_reference() {
+        let mut rng = Rng(0x5c7a);
+        for _ in 0..300 {
+            let cards = rng.below(30);
+            let (deck, matches) = random_deck(&mut rng, cards);
+            let text = deck.join("\n");
+            let (points, total, copies) = reference(&matches);
+            let (sum_part_1, sum_part_2, overreaches) = calculate_wins(lines(&text)).unwrap();
+            assert_eq!((sum_part_1, sum_part_2), (points, total), "\n{}", text);
+            // every card counts at least once
+            assert!(sum_part_2 >= cards as u128);
+            let reaching_past_end = (1..).zip(&matches).filter(|(id, m)| id + *m > cards).count();
+            assert_eq!(overreaches.len(), reaching_past_end);
+
+            let (traces, cascade_overreaches) = trace_cascade(&read_deck(lines(&text), DeckOrder::Strict).unwrap()).unwrap();
+            assert_eq!(traces.iter().map(|trace| trace.copies).collect::<Vec<_>>(), copies);
+            assert_eq!(cascade_overreaches, overreaches);
+        }
+    }
+
+    #[test]
+    fn test_shuffled_decks_score_the_same() {
+        let mut rng = Rng(0xd3c);
+        for _ in 0..200 {
+            let cards = rng.below(30);
+            let (mut deck, _) = random_deck(&mut rng, cards);
+            let expected = calculate_wins(lines(&deck.join("\n"))).unwrap();
+            for i in (1..deck.len()).rev() {
+                deck.swap(i, rng.below(i + 1));
+            }
+            assert_eq!(calculate_wins_unordered(lines(&deck.join("\n"))).unwrap(), expected);
+        }
+    }
+}
@@ -2,22 +2,117 @@ use std::io::{self, BufRead};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     let stdin = io::stdin();
-    let lines = stdin.lock().lines();
+    let lines: Vec<Result<String, io::Error>> = stdin.lock().lines().collect();
 
-    let input = read_maps(lines)?;
+    // almanacs are read in the puzzle format or as JSON
+    let input = match lines.iter().flatten().find(|line| !line.trim().is_empty()) {
+        Some(line) if line.trim_start().starts_with('{') => {
+            let text = lines.into_iter().collect::<Result<Vec<String>, io::Error>>()?.join("\n");
+            read_json(&text)?
+        }
+        _ => read_maps(lines.into_iter())?,
+    };
 
-    let lowest_location_part_1 = part_1(&input)?;
-    let lowest_location_part_2 = part_2_brute_backward(&input)?;
-    println!("lowest location of individual seeds {}", lowest_location_part_1);
-    println!("lowest location of seed ranges {}", lowest_location_part_2);
+    let args: Vec<String> = std::env::args().collect();
+    // --from and --to select the categories for convert and compose
+    let from = flag_value(&args, "--from").unwrap_or("seed");
+    let to = flag_value(&args, "--to").unwrap_or("location");
+    match args.get(1).map(String::as_str) {
+        None => {
+            let lowest_location_part_1 = part_1(&input)?;
+            let lowest_location_part_2 = part_2(&input)?;
+            println!("lowest location of individual seeds {}", lowest_location_part_1);
+            println!("lowest location of seed ranges {}", lowest_location_part_2);
+        }
+        // convert converts the given values (or the seeds) from one category to another
+        Some("convert") => {
+            let chain = input.chain(from, to)?;
+            let values = match positional_args(&args[2..]) {
+                values if values.is_empty() => input.seeds.clone(),
+                values => values.iter().map(|value| value.parse::<usize>()).collect::<Result<_, _>>()?,
+            };
+            for value in values {
+                println!("{} {} -> {} {}", from, value, to, chain.convert(value));
+            }
+        }
+        // brute converts every single seed of the seed ranges as a cross-check for part 2.
+        // --threads sets the amount of workers and defaults to the available parallelism.
+        Some("brute") => {
+            let workers = match flag_value(&args, "--threads") {
+                Some(threads) => threads.parse::<usize>()?,
+                None => std::thread::available_parallelism().map_or(1, usize::from),
+            };
+            println!("lowest location of seed ranges (brute force) {}", part_2_brute_forward_parallel(&input, workers)?);
+        }
+        // trace prints the lineage of the given values (or the seeds) and the ranges covered by the seed ranges
+        Some("trace") => {
+            let chain = input.chain(from, to)?;
+            let values: Vec<usize> = positional_args(&args[2..]).iter().map(|value| value.parse::<usize>()).collect::<Result<_, _>>()?;
+            let trace_seed_ranges = values.is_empty();
+            for value in if trace_seed_ranges { input.seeds.clone() } else { values } {
+                println!("{} {}", from, value);
+                for step in chain.trace(value) {
+                    println!("  {}", step);
+                }
+            }
+            if trace_seed_ranges {
+                for (start, length) in &input.seed_ranges {
+                    println!("{} range {}", from, fmt_range((*start, *length)));
+                    for (category, ranges) in chain.trace_range(*start, *length) {
+                        let ranges: Vec<String> = ranges.into_iter().map(fmt_range).collect();
+                        println!("  {}: {}", category, ranges.join(", "));
+                    }
+                }
+            }
+        }
+        // validate reports overlapping entries, maps that can not be inverted and gaps
+        Some("validate") => {
+            let issues = input.validate();
+            for issue in &issues {
+                println!("{}", issue);
+            }
+            let errors = issues.iter().filter(|issue| issue.is_error()).count();
+            if errors > 0 {
+                return Err(format!("almanac has {} errors", errors).into());
+            }
+        }
+        // format prints the almanac in canonical form
+        Some("format") => print!("{}", input),
+        Some("json") => print!("{}", input.to_json()),
+        // compose prints the chain of maps as a single map
+        Some("compose") => {
+            let composed = input.chain(from, to)?.compose().ok_or(format!("no maps to compose from {} to {}", from, to))?;
+            print!("{}", composed);
+        }
+        Some(command) => return Err(format!("unknown command: {}", command).into()),
+    }
 
     Ok(())
 }
 
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1)).map(String::as_str)
+}
+
+// positional_args skips all flags and their values
+fn positional_args(args: &[String]) -> Vec<&String> {
+    let mut positional = Vec::new();
+    let mut args = args.iter();
+    while let Some(arg) = args.next() {
+        if arg.starts_with("--") {
+            args.next();
+        } else {
+            positional.push(arg);
+        }
+    }
+    positional
+}
+
 fn part_1(input: &Input) -> Result<usize, Box<dyn std::error::Error>> {
+    let chain = input.chain("seed", "location")?;
     let mut smallest_location: usize = usize::MAX;
     for seed in &input.seeds {
-        let converted = input.convert(*seed);
+        let converted = chain.convert(*seed);
         if converted < smallest_location {
             smallest_location = converted;
         }
@@ -25,13 +120,32 @@ fn part_1(input: &Input) -> Result<usize, Box<dyn std::error::Error>> {
     Ok(smallest_location)
 }
 
+// part_2 converts the seed ranges as a whole. Every range is split at the boundaries of the conversion entries,
+// so the running time only depends on the amount of ranges and entries.
+fn part_2(input: &Input) -> Result<usize, Box<dyn std::error::Error>> {
+    let chain = input.chain("seed", "location")?;
+    let mut smallest_location: usize = usize::MAX;
+    for (start, length) in &input.seed_ranges {
+        for (converted_start, _) in chain.convert_range(*start, *length) {
+            if converted_start < smallest_location {
+                smallest_location = converted_start;
+            }
+        }
+    }
+    if smallest_location == usize::MAX {
+        return Err("no solution found".into());
+    }
+    Ok(smallest_location)
+}
+
 // theoretically, this is the simplest solution for day 2, however, it is too slow by some orders of magnitudes.
 #[allow(dead_code)]
 fn part_2_brute_forward(input: &Input) -> Result<usize, Box<dyn std::error::Error>> {
+    let chain = input.chain("seed", "location")?;
     let mut smallest_location: usize = usize::MAX;
     for seed_range in input.seeds.chunks_exact(2) {
         for seed in seed_range[0]..seed_range[0]+seed_range[1] {
-            let converted = input.convert(seed);
+            let converted = chain.convert(seed);
             if converted < smallest_location {
                 smallest_location = converted;
             }
@@ -40,9 +154,56 @@ fn part_2_brute_forward(input: &Input) -> Result<usize, Box<dyn std::error::Erro
     Ok(smallest_location)
 }
 
+// seeds per chunk of work for part_2_brute_forward_parallel
+const BRUTE_FORCE_CHUNK_SIZE: usize = 1 << 16;
+
+// part_2_brute_forward_parallel is part_2_brute_forward spread over worker threads.
+// The seed ranges are cut into chunks and every worker takes the next chunk as soon as it is done with its last one,
+// so a worker that got a cheap chunk does not sit idle. The lowest location is shared between workers as an atomic.
+// Workers take chunk indexes from a shared counter and compute the seeds of a chunk from its index, so memory does not
+// grow with the amount of seeds.
+fn part_2_brute_forward_parallel(input: &Input, workers: usize) -> Result<usize, Box<dyn std::error::Error>> {
+    let chain = input.chain("seed", "location")?;
+    let workers = std::cmp::max(workers, 1);
+    // chunk_ends[i] is the index after the last chunk of seed range i
+    let mut chunk_ends = Vec::with_capacity(input.seed_ranges.len());
+    let mut chunks: usize = 0;
+    for (_, length) in &input.seed_ranges {
+        // every worker takes one index past the last chunk, so those have to fit as well
+        chunks = chunks
+            .checked_add(length.div_ceil(BRUTE_FORCE_CHUNK_SIZE))
+            .filter(|chunks| chunks.checked_add(workers).is_some())
+            .ok_or("too many seeds to brute force")?;
+        chunk_ends.push(chunks);
+    }
+    let next_chunk = std::sync::atomic::AtomicUsize::new(0);
+    let smallest_location = std::sync::atomic::AtomicUsize::new(usize::MAX);
+    std::thread::scope(|scope| {
+        for _ in 0..workers {
+            scope.spawn(|| loop {
+                let chunk = next_chunk.fetch_add(1, std::sync::atomic::Ordering::Relaxed);
+                // empty seed ranges have no chunks and are skipped here
+                let range = chunk_ends.partition_point(|end| *end <= chunk);
+                let Some((start, length)) = input.seed_ranges.get(range) else {
+                    break;
+                };
+                let first_chunk = if range == 0 { 0 } else { chunk_ends[range - 1] };
+                let chunk_start = start + (chunk - first_chunk) * BRUTE_FORCE_CHUNK_SIZE;
+                let chunk_end = std::cmp::min(chunk_start.saturating_add(BRUTE_FORCE_CHUNK_SIZE), start + length);
+                let converted = (chunk_start..chunk_end).map(|seed| chain.convert(seed)).min().unwrap_or(usize::MAX);
+                smallest_location.fetch_min(converted, std::sync::atomic::Ordering::Relaxed);
+            });
+        }
+    });
+    Ok(smallest_location.into_inner())
+}
+
+// part_2_brute_backward is kept as a reference for part_2. Its running time grows with the answer.
+#[allow(dead_code)]
 fn part_2_brute_backward(input: &Input) -> Result<usize, Box<dyn std::error::Error>> {
-   for location in 0.. {
-        let converted = input.reverse_convert(location);
+    let chain = input.reverse_chain("seed", "location")?;
+    for location in 0.. {
+        let converted = chain.convert(location);
         if input.seed_in_range(converted) {
             return Ok(location);
         }
@@ -53,17 +214,33 @@ fn part_2_brute_backward(input: &Input) -> Result<usize, Box<dyn std::error::Err
 fn read_maps(mut lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<Input, Box<dyn std::error::Error>> {
     let seeds = read_seeds(&lines.next().ok_or("invalid input: expected a line with seeds")??).ok_or("invalid input")?;
     lines.next(); // skip empty line
-    let mut maps = std::collections::HashMap::new();
-    let mut reverse_maps = std::collections::HashMap::new();
-    while let Some((map, reverse_map)) = read_map(&mut lines)? {
-        maps.insert(map.from.clone(), map);
-        reverse_maps.insert(reverse_map.from.clone(), reverse_map);
+    let mut maps = Vec::new();
+    while let Some((map, _)) = read_map(&mut lines)? {
+        maps.push(map);
+    }
+    build_input(seeds.0, maps)
+}
+
+// build_input adds the reverse maps and rejects duplicate maps and seed ranges that reach past usize::MAX.
+fn build_input(seeds: Vec<usize>, conversions: Vec<Conversion>) -> Result<Input, Box<dyn std::error::Error>> {
+    if let Some(seed_range) = seeds.chunks_exact(2).find(|seed_range| seed_range[0].checked_add(seed_range[1]).is_none()) {
+        return Err(format!("invalid input: seed range {} {} reaches past {}", seed_range[0], seed_range[1], usize::MAX).into());
+    }
+    let mut maps = CategoryGraph::new();
+    let mut reverse_maps = CategoryGraph::new();
+    for map in conversions {
+        let branches: &mut Vec<Conversion> = maps.entry(map.from.clone()).or_default();
+        if branches.iter().any(|branch| branch.to == map.to) {
+            return Err(format!("invalid input: duplicate {}-to-{} map", map.from, map.to).into());
+        }
+        reverse_maps.entry(map.to.clone()).or_default().push(map.reversed());
+        branches.push(map);
     }
     let input = Input {
-        seeds: seeds.0,
-        seed_ranges: seeds.1,
-        maps: maps,
-        reverse_maps: reverse_maps,
+        seed_ranges: seed_ranges(&seeds),
+        seeds,
+        maps,
+        reverse_maps,
     };
     Ok(input)
 }
@@ -77,13 +254,14 @@ fn read_seeds(line: &str) -> Option<(Vec<usize>, SeedRanges)> {
     for seed in line.split_whitespace() {
         seeds.push(seed.parse::<usize>().ok()?);
     }
-    let mut seed_ranges = SeedRanges::new();
-    for seed_range in seeds.chunks_exact(2) {
-        seed_ranges.insert(seed_range[0], seed_range[1]);
-    }
+    let seed_ranges = seed_ranges(&seeds);
     Some((seeds, seed_ranges))
 }
 
+fn seed_ranges(seeds: &[usize]) -> SeedRanges {
+    seeds.chunks_exact(2).map(|seed_range| (seed_range[0], seed_range[1])).collect()
+}
+
 fn read_conversion_entry(line: &str) -> Option<ConversionEntry> {
     let mut parts = line.split_whitespace();
     let destination_range_start = parts.next()?.parse::<usize>().ok()?;
@@ -102,29 +280,23 @@ fn read_map(mut lines: impl Iterator<Item = Result<String, io::Error>>) -> Resul
         return Ok(None);
     }
     let description = description.ok_or("invalid map")??;
-    let key_to_value = description.split_once(" ").ok_or("invalid map description line: expected space")?;
-    let mut parts = key_to_value.0.split("-");
-    let key = parts.next().ok_or("invalid map description line: expected map key")?;
-    parts.next();
-    let value = parts.next().ok_or("invalid map description line: expected map value")?;
-
-    let mut conversion_fwd = Conversion::new(&key, &value);
-    let mut conversion_reverse = Conversion::new(&value, &key);
+    let key_to_value = description.strip_suffix(" map:").ok_or("invalid map description line: expected \"KEY-to-VALUE map:\"")?;
+    let (key, value) = key_to_value.split_once("-to-").ok_or("invalid map description line: expected \"-to-\"")?;
+    if key.is_empty() || value.is_empty() || value.contains("-to-") {
+        return Err(format!("invalid map description line: {}", description).into());
+    }
+
+    let mut conversion_fwd = Conversion::new(key, value);
     for line in lines {
         let line = line?;
         if line.is_empty() {
             break;
         }
         if let Some(conv) = read_conversion_entry(&line) {
-            conversion_fwd.insert(conv.source_range_start, conv.clone());
-            let reverse_conv = ConversionEntry {
-                destination_range_start: conv.source_range_start,
-                source_range_start: conv.destination_range_start,
-                range_length: conv.range_length,
-            };
-            conversion_reverse.insert(reverse_conv.source_range_start, reverse_conv);
+            conversion_fwd.insert_new(conv)?;
         }
     }
+    let conversion_reverse = conversion_fwd.reversed();
     Ok(Some((conversion_fwd, conversion_reverse)))
 }
 
@@ -136,8 +308,13 @@ struct ConversionEntry {
 }
 type ConversionMap = std::collections::BTreeMap<usize, ConversionEntry>;
 
-type SeedRanges = std::collections::BTreeMap<usize, usize>;
+// SeedRanges holds the (start, length) pairs in input order, ranges may share a start or overlap.
+type SeedRanges = Vec<(usize, usize)>;
+
+// CategoryGraph holds all conversions keyed by the category they convert from.
+type CategoryGraph = std::collections::HashMap<String, Vec<Conversion>>;
 
+#[derive(Clone, Debug, PartialEq)]
 struct Conversion{
     map: ConversionMap,
     from: String,
@@ -157,51 +334,703 @@ impl Conversion {
         self.map.insert(key, value);
     }
 
+    // insert_new inserts an entry read from an almanac and rejects a second entry for the same source.
+    // Source and destination ranges have to end at usize::MAX at the latest, so converting values never overflows.
+    fn insert_new(&mut self, conv: ConversionEntry) -> Result<(), Box<dyn std::error::Error>> {
+        if self.map.contains_key(&conv.source_range_start) {
+            return Err(format!("invalid map: duplicate entry for source {} in {}-to-{} map", conv.source_range_start, self.from, self.to).into());
+        }
+        let range_start = std::cmp::max(conv.source_range_start, conv.destination_range_start);
+        if range_start.checked_add(conv.range_length).is_none() {
+            return Err(format!(
+                "invalid map: entry {} {} {} reaches past {} in {}-to-{} map",
+                conv.destination_range_start, conv.source_range_start, conv.range_length, usize::MAX, self.from, self.to,
+            ).into());
+        }
+        self.insert(conv.source_range_start, conv);
+        Ok(())
+    }
+
+    fn reversed(&self) -> Conversion {
+        let mut reverse = Conversion::new(&self.to, &self.from);
+        for conv in self.map.values() {
+            reverse.insert(conv.destination_range_start, ConversionEntry {
+                destination_range_start: conv.source_range_start,
+                source_range_start: conv.destination_range_start,
+                range_length: conv.range_length,
+            });
+        }
+        reverse
+    }
+
+    // canonical drops empty entries and merges entries that continue each other.
+    fn canonical(&self) -> Conversion {
+        let mut canonical = Conversion::new(&self.from, &self.to);
+        for conv in self.map.values().filter(|conv| conv.range_length > 0) {
+            canonical.push_merged(conv.clone());
+        }
+        canonical
+    }
+
     fn lookup(&self, i: usize) -> usize {
-        let conv = match self.map.range(..=i).next_back() {
-            None => return i,
-            Some(conv) => conv.1,
-        };
-        if i > (conv.source_range_start + conv.range_length) {
-            return i;
+        match self.find_entry(i) {
+            None => i,
+            Some(conv) => i - conv.source_range_start + conv.destination_range_start,
+        }
+    }
+
+    // find_entry returns the entry converting i, if any.
+    fn find_entry(&self, i: usize) -> Option<&ConversionEntry> {
+        let conv = self.map.range(..=i).next_back()?.1;
+        // entries cover the half-open range [source_range_start, source_range_start + range_length)
+        if i >= conv.source_range_start + conv.range_length {
+            return None;
+        }
+        Some(conv)
+    }
+
+    // lookup_range converts the range [start, start + length) and returns the converted sub-ranges as (start, length).
+    // Parts of the range that are not covered by an entry map to themselves.
+    fn lookup_range(&self, start: usize, length: usize) -> Vec<(usize, usize)> {
+        let mut converted = Vec::new();
+        let end = start + length;
+        let mut current = start;
+        // the entry containing start (if any) begins at or before start
+        let first_key = self.map.range(..=start).next_back().map_or(start, |(key, _)| *key);
+        for conv in self.map.range(first_key..end).map(|(_, conv)| conv) {
+            let conv_end = conv.source_range_start + conv.range_length;
+            if conv_end <= current {
+                continue;
+            }
+            if conv.source_range_start > current {
+                converted.push((current, conv.source_range_start - current));
+                current = conv.source_range_start;
+            }
+            let sub_range_end = std::cmp::min(conv_end, end);
+            converted.push((current - conv.source_range_start + conv.destination_range_start, sub_range_end - current));
+            current = sub_range_end;
+        }
+        if current < end {
+            converted.push((current, end - current));
+        }
+        converted
+    }
+
+    fn validate(&self) -> Vec<Issue> {
+        let name = format!("{}-to-{}", self.from, self.to);
+        let mut issues = Vec::new();
+        let entries: Vec<&ConversionEntry> = self.map.values().filter(|conv| conv.range_length > 0).collect();
+
+        // (destination start, source start, length) of every entry and of every identity mapped range
+        let mut destinations = Vec::new();
+        let mut covered_until = 0;
+        let mut widest: Option<&ConversionEntry> = None;
+        for conv in &entries {
+            let conv_end = conv.source_range_start + conv.range_length;
+            match widest {
+                Some(prev) if conv.source_range_start < covered_until => issues.push(Issue::OverlappingSources {
+                    map: name.clone(),
+                    first: (prev.source_range_start, prev.range_length),
+                    second: (conv.source_range_start, conv.range_length),
+                }),
+                Some(_) if conv.source_range_start > covered_until => issues.push(Issue::Gap {
+                    map: name.clone(),
+                    start: covered_until,
+                    length: conv.source_range_start - covered_until,
+                }),
+                _ => (),
+            }
+            if conv.source_range_start > covered_until {
+                destinations.push((covered_until, covered_until, conv.source_range_start - covered_until));
+            }
+            destinations.push((conv.destination_range_start, conv.source_range_start, conv.range_length));
+            if conv_end > covered_until {
+                covered_until = conv_end;
+                widest = Some(conv);
+            }
+        }
+        if covered_until < usize::MAX {
+            destinations.push((covered_until, covered_until, usize::MAX - covered_until));
+        }
+
+        destinations.sort();
+        let mut widest_destination: Option<(usize, usize, usize)> = None;
+        for destination in destinations {
+            if let Some(prev) = widest_destination {
+                if destination.0 < prev.0 + prev.2 {
+                    let overlap_end = std::cmp::min(prev.0 + prev.2, destination.0 + destination.2);
+                    issues.push(Issue::OverlappingDestinations {
+                        map: name.clone(),
+                        first: (prev.1, prev.2),
+                        second: (destination.1, destination.2),
+                        destination: (destination.0, overlap_end - destination.0),
+                    });
+                }
+                if destination.0 + destination.2 <= prev.0 + prev.2 {
+                    continue;
+                }
+            }
+            widest_destination = Some(destination);
+        }
+        issues
+    }
+
+    // then returns a single conversion that applies self first and next afterwards.
+    // Only the parts that do not map to themselves are stored as entries.
+    fn then(&self, next: &Conversion) -> Conversion {
+        let mut composed = Conversion::new(&self.from, &next.to);
+        let mut source = 0;
+        for (destination, length) in self.lookup_range(0, usize::MAX) {
+            for (final_destination, sub_length) in next.lookup_range(destination, length) {
+                if final_destination != source {
+                    composed.push_merged(ConversionEntry {
+                        destination_range_start: final_destination,
+                        source_range_start: source,
+                        range_length: sub_length,
+                    });
+                }
+                source += sub_length;
+            }
+        }
+        composed
+    }
+
+    // push_merged inserts an entry that starts after all existing entries.
+    // It is merged into the last entry if both continue each other.
+    fn push_merged(&mut self, entry: ConversionEntry) {
+        if let Some((_, last)) = self.map.iter_mut().next_back() {
+            if last.source_range_start + last.range_length == entry.source_range_start
+                && last.destination_range_start + last.range_length == entry.destination_range_start {
+                last.range_length += entry.range_length;
+                return;
+            }
         }
-        i - conv.source_range_start + conv.destination_range_start
+        self.insert(entry.source_range_start, entry);
+    }
+}
+
+// Issue is a problem found while validating a map.
+// Source ranges are given as (start, length).
+#[derive(Debug, PartialEq)]
+enum Issue {
+    // two entries convert the same source values
+    OverlappingSources { map: String, first: (usize, usize), second: (usize, usize) },
+    // two source ranges convert to the same destination values, so the map can not be inverted.
+    // Source ranges without an entry take part as they map to themselves.
+    OverlappingDestinations { map: String, first: (usize, usize), second: (usize, usize), destination: (usize, usize) },
+    // source values between two entries are not covered and map to themselves
+    Gap { map: String, start: usize, length: usize },
+}
+
+impl Issue {
+    // gaps are legitimate, all other issues make the map ambiguous or non-invertible
+    fn is_error(&self) -> bool {
+        !matches!(self, Issue::Gap { .. })
+    }
+}
+
+impl std::fmt::Display for Issue {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            Issue::OverlappingSources { map, first, second } => write!(
+                f, "{}: source ranges {} and {} overlap", map, fmt_range(*first), fmt_range(*second),
+            ),
+            Issue::OverlappingDestinations { map, first, second, destination } => write!(
+                f, "{}: source ranges {} and {} both map to {}", map, fmt_range(*first), fmt_range(*second), fmt_range(*destination),
+            ),
+            Issue::Gap { map, start, length } => write!(f, "{}: gap {} maps to itself", map, fmt_range((*start, *length))),
+        }
+    }
+}
+
+// fmt_range writes (start, length) as a half-open range. Ranges reaching the end of usize are left open.
+fn fmt_range(range: (usize, usize)) -> String {
+    match range.0.checked_add(range.1) {
+        Some(usize::MAX) | None => format!("{}..", range.0),
+        Some(end) => format!("{}..{}", range.0, end),
+    }
+}
+
+// Conversions are written in the almanac format: "FROM-to-TO map:" followed by "DESTINATION SOURCE LENGTH" lines.
+impl std::fmt::Display for Conversion {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        writeln!(f, "{}-to-{} map:", self.from, self.to)?;
+        for conv in self.map.values() {
+            writeln!(f, "{} {} {}", conv.destination_range_start, conv.source_range_start, conv.range_length)?;
+        }
+        Ok(())
     }
 }
 
 struct Input {
     seeds: Vec<usize>,
     seed_ranges: SeedRanges,
-    maps: std::collections::HashMap<String, Conversion>,
-    reverse_maps: std::collections::HashMap<String, Conversion>,
+    maps: CategoryGraph,
+    reverse_maps: CategoryGraph,
 }
 
 impl Input {
+    // seed_in_range checks every seed range, since seed ranges may overlap.
     fn seed_in_range(&self, seed: usize) -> bool {
-        let range = match self.seed_ranges.range(..=seed).next_back() {
-            None => return false,
-            Some(range) => range,
-        };
-        return seed > *range.0 && seed < (range.0 + range.1);
-    }
-    fn convert(&self, seed: usize) -> usize {
-        let mut current = seed;
-        let mut map_name = "seed".to_string();
-        while let Some(map) = self.maps.get(&map_name) {
-            current = map.lookup(current);
-            map_name = map.to.clone();
+        self.seed_ranges.iter().any(|(start, length)| *start <= seed && seed < start + length)
+    }
+
+    // validate checks every map of the almanac, sorted by name.
+    fn validate(&self) -> Vec<Issue> {
+        let mut maps: Vec<&Conversion> = self.maps.values().flatten().collect();
+        maps.sort_by(|a, b| (&a.from, &a.to).cmp(&(&b.from, &b.to)));
+        maps.into_iter().flat_map(|map| map.validate()).collect()
+    }
+
+    // chain finds the only path of maps from one category to another.
+    // Missing paths, multiple paths (listing the first two) and cycles on the way are reported as errors.
+    fn chain(&self, from: &str, to: &str) -> Result<Chain<'_>, Box<dyn std::error::Error>> {
+        let mut paths = find_paths(&self.maps, to, &mut vec![from], &mut std::collections::HashMap::new())?;
+        match paths.len() {
+            0 => Err(format!("no path from {} to {}", from, to).into()),
+            1 => Ok(Chain { conversions: paths.remove(0) }),
+            _ => {
+                let paths: Vec<String> = paths.iter().map(|path| Chain { conversions: path.clone() }.to_string()).collect();
+                Err(format!("ambiguous paths from {} to {}: {}", from, to, paths.join(", ")).into())
+            }
         }
-        current
     }
-    fn reverse_convert(&self, seed: usize) -> usize {
-        let mut current = seed;
-        let mut map_name = "location".to_string();
-        while let Some(map) = self.reverse_maps.get(&map_name) {
-            current = map.lookup(current);
-            map_name = map.to.clone();
+
+    // reverse_chain returns the chain that converts values of `to` back to `from`.
+    fn reverse_chain(&self, from: &str, to: &str) -> Result<Chain<'_>, Box<dyn std::error::Error>> {
+        let forward = self.chain(from, to)?;
+        let mut conversions = Vec::new();
+        for conv in forward.conversions.iter().rev() {
+            if conv.validate().iter().any(Issue::is_error) {
+                return Err(format!("{}-to-{} map is not invertible", conv.from, conv.to).into());
+            }
+            let reverse = self.reverse_maps.get(&conv.to).into_iter().flatten().find(|reverse| reverse.to == conv.from);
+            conversions.push(reverse.ok_or(format!("no reverse map for {}-to-{}", conv.from, conv.to))?);
+        }
+        Ok(Chain { conversions })
+    }
+}
+
+impl Input {
+    // sorted_maps orders the maps along the category graph, starting at categories without incoming maps.
+    // Maps that are part of a cycle come last, sorted by name.
+    fn sorted_maps(&self) -> Vec<&Conversion> {
+        let mut incoming: std::collections::BTreeMap<&str, usize> = std::collections::BTreeMap::new();
+        for map in self.maps.values().flatten() {
+            incoming.entry(&map.from).or_default();
+            *incoming.entry(&map.to).or_default() += 1;
+        }
+        let mut ready: std::collections::BTreeSet<&str> = incoming.iter().filter(|(_, count)| **count == 0).map(|(category, _)| *category).collect();
+        let mut order: Vec<&str> = Vec::new();
+        while let Some(category) = ready.pop_first() {
+            order.push(category);
+            for map in self.maps.get(category).into_iter().flatten() {
+                let count = incoming.get_mut(map.to.as_str()).unwrap();
+                *count -= 1;
+                if *count == 0 {
+                    ready.insert(&map.to);
+                }
+            }
+        }
+        let mut maps: Vec<&Conversion> = self.maps.values().flatten().collect();
+        maps.sort_by_key(|map| (order.iter().position(|category| *category == map.from).unwrap_or(order.len()), &map.from, &map.to));
+        maps
+    }
+
+    fn to_json(&self) -> String {
+        let seeds: Vec<String> = self.seeds.iter().map(|seed| seed.to_string()).collect();
+        let mut json = format!("{{\n  \"seeds\": [{}],\n  \"maps\": [", seeds.join(", "));
+        for (i, map) in self.sorted_maps().into_iter().enumerate() {
+            let map = map.canonical();
+            json += if i == 0 { "\n" } else { ",\n" };
+            json += &format!("    {{\n      \"from\": {},\n      \"to\": {},\n      \"entries\": [", json_string(&map.from), json_string(&map.to));
+            for (j, conv) in map.map.values().enumerate() {
+                json += if j == 0 { "\n" } else { ",\n" };
+                json += &format!(
+                    "        {{\"destination\": {}, \"source\": {}, \"length\": {}}}",
+                    conv.destination_range_start, conv.source_range_start, conv.range_length,
+                );
+            }
+            json += if map.map.is_empty() { "]\n    }" } else { "\n      ]\n    }" };
+        }
+        json += if self.maps.is_empty() { "]\n}\n" } else { "\n  ]\n}\n" };
+        json
+    }
+}
+
+// The almanac is written in canonical form: maps follow the category graph and every map is canonical.
+impl std::fmt::Display for Input {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let seeds: Vec<String> = self.seeds.iter().map(|seed| seed.to_string()).collect();
+        writeln!(f, "seeds: {}", seeds.join(" "))?;
+        for map in self.sorted_maps() {
+            write!(f, "\n{}", map.canonical())?;
+        }
+        Ok(())
+    }
+}
+
+// read_json reads an almanac in the format written by Input::to_json.
+fn read_json(text: &str) -> Result<Input, Box<dyn std::error::Error>> {
+    let json = parse_json(text)?;
+    let seeds = json.field("seeds")?.array()?.iter().map(Json::number).collect::<Result<Vec<usize>, String>>()?;
+    let mut maps = Vec::new();
+    for map in json.field("maps")?.array()? {
+        let from = map.field("from")?.string()?;
+        let to = map.field("to")?.string()?;
+        // the map has to read back as the same pair of categories from its "FROM-to-TO map:" line
+        let name = format!("{}-to-{}", from, to);
+        if from.is_empty() || to.is_empty() || to.contains("-to-") || from.contains('\n') || to.contains('\n') || name.split_once("-to-") != Some((from, to)) {
+            return Err(format!("invalid map name: {}", name).into());
+        }
+        let mut conversion = Conversion::new(from, to);
+        for entry in map.field("entries")?.array()? {
+            conversion.insert_new(ConversionEntry {
+                destination_range_start: entry.field("destination")?.number()?,
+                source_range_start: entry.field("source")?.number()?,
+                range_length: entry.field("length")?.number()?,
+            })?;
+        }
+        maps.push(conversion);
+    }
+    build_input(seeds, maps)
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json += "\\\"",
+            '\\' => json += "\\\\",
+            '\n' => json += "\\n",
+            c if c.is_control() => json += &format!("\\u{:04x}", c as u32),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+// Json is the subset of JSON used for almanacs: objects, arrays, strings and non-negative integers.
+#[derive(Debug, PartialEq)]
+enum Json {
+    Number(usize),
+    String(String),
+    Array(Vec<Json>),
+    Object(Vec<(String, Json)>),
+}
+
+impl Json {
+    fn field(&self, name: &str) -> Result<&Json, String> {
+        match self {
+            Json::Object(fields) => fields.iter().find(|(key, _)| key == name).map(|(_, value)| value).ok_or(format!("missing field {}", name)),
+            _ => Err(format!("expected an object with field {}", name)),
+        }
+    }
+
+    fn array(&self) -> Result<&[Json], String> {
+        match self {
+            Json::Array(values) => Ok(values),
+            _ => Err("expected an array".to_string()),
+        }
+    }
+
+    fn string(&self) -> Result<&str, String> {
+        match self {
+            Json::String(s) => Ok(s),
+            _ => Err("expected a string".to_string()),
+        }
+    }
+
+    fn number(&self) -> Result<usize, String> {
+        match self {
+            Json::Number(n) => Ok(*n),
+            _ => Err("expected a number".to_string()),
+        }
+    }
+}
+
+// objects and arrays nested deeper than this are rejected, so the recursive parser can't overflow the stack
+const MAX_JSON_DEPTH: usize = 64;
+
+fn parse_json(text: &str) -> Result<Json, String> {
+    let mut parser = JsonParser { text, position: 0, depth: 0 };
+    let value = parser.value()?;
+    parser.skip_whitespace();
+    if parser.position < text.len() {
+        return Err(format!("unexpected character at position {}", parser.position));
+    }
+    Ok(value)
+}
+
+struct JsonParser<'a> {
+    text: &'a str,
+    position: usize,
+    // objects and arrays that are currently open
+    depth: usize,
+}
+
+impl JsonParser<'_> {
+    fn peek(&self) -> Option<char> {
+        self.text[self.position..].chars().next()
+    }
+
+    fn next(&mut self) -> Option<char> {
+        let c = self.peek()?;
+        self.position += c.len_utf8();
+        Some(c)
+    }
+
+    fn skip_whitespace(&mut self) {
+        while self.peek().is_some_and(|c| c.is_ascii_whitespace()) {
+            self.position += 1;
+        }
+    }
+
+    fn expect(&mut self, expected: char) -> Result<(), String> {
+        self.skip_whitespace();
+        match self.next() {
+            Some(c) if c == expected => Ok(()),
+            _ => Err(format!("expected '{}' at position {}", expected, self.position)),
+        }
+    }
+
+    fn value(&mut self) -> Result<Json, String> {
+        self.skip_whitespace();
+        match self.peek() {
+            Some('{') => self.nested(Self::object),
+            Some('[') => self.nested(Self::array),
+            Some('"') => Ok(Json::String(self.string()?)),
+            Some('0'..='9') => self.number(),
+            Some(c) => Err(format!("unexpected character '{}' at position {}", c, self.position)),
+            None => Err("unexpected end of json".to_string()),
+        }
+    }
+
+    fn nested(&mut self, parse: impl FnOnce(&mut Self) -> Result<Json, String>) -> Result<Json, String> {
+        if self.depth == MAX_JSON_DEPTH {
+            return Err(format!("nesting too deep at position {}", self.position));
+        }
+        self.depth += 1;
+        let value = parse(self);
+        self.depth -= 1;
+        value
+    }
+
+    // list parses the comma separated items between open and close
+    fn list(&mut self, open: char, close: char, mut item: impl FnMut(&mut Self) -> Result<(), String>) -> Result<(), String> {
+        self.expect(open)?;
+        self.skip_whitespace();
+        if self.peek() == Some(close) {
+            self.position += 1;
+            return Ok(());
+        }
+        loop {
+            item(self)?;
+            self.skip_whitespace();
+            match self.next() {
+                Some(',') => (),
+                Some(c) if c == close => return Ok(()),
+                _ => return Err(format!("expected ',' or '{}' at position {}", close, self.position)),
+            }
+        }
+    }
+
+    fn object(&mut self) -> Result<Json, String> {
+        let mut fields = Vec::new();
+        self.list('{', '}', |parser| {
+            parser.skip_whitespace();
+            let key = parser.string()?;
+            parser.expect(':')?;
+            fields.push((key, parser.value()?));
+            Ok(())
+        })?;
+        Ok(Json::Object(fields))
+    }
+
+    fn array(&mut self) -> Result<Json, String> {
+        let mut values = Vec::new();
+        self.list('[', ']', |parser| {
+            values.push(parser.value()?);
+            Ok(())
+        })?;
+        Ok(Json::Array(values))
+    }
+
+    fn string(&mut self) -> Result<String, String> {
+        self.expect('"')?;
+        let mut s = String::new();
+        loop {
+            match self.next() {
+                Some('"') => return Ok(s),
+                Some('\\') => match self.next() {
+                    Some('"') => s.push('"'),
+                    Some('\\') => s.push('\\'),
+                    Some('/') => s.push('/'),
+                    Some('n') => s.push('\n'),
+                    Some('t') => s.push('\t'),
+                    Some('r') => s.push('\r'),
+                    Some('b') => s.push('\u{8}'),
+                    Some('f') => s.push('\u{c}'),
+                    Some('u') => {
+                        let hex = self.text.get(self.position..self.position + 4).ok_or("unexpected end of json")?;
+                        // from_str_radix alone would accept a sign
+                        if !hex.bytes().all(|b| b.is_ascii_hexdigit()) {
+                            return Err(format!("invalid escape at position {}", self.position));
+                        }
+                        let code = u32::from_str_radix(hex, 16).map_err(|_| format!("invalid escape at position {}", self.position))?;
+                        s.push(char::from_u32(code).ok_or(format!("invalid escape at position {}", self.position))?);
+                        self.position += 4;
+                    }
+                    _ => return Err(format!("invalid escape at position {}", self.position)),
+                },
+                Some(c) => s.push(c),
+                None => return Err("unterminated string".to_string()),
+            }
+        }
+    }
+
+    fn number(&mut self) -> Result<Json, String> {
+        let start = self.position;
+        while self.peek().is_some_and(|c| c.is_ascii_digit()) {
+            self.position += 1;
+        }
+        let number = &self.text[start..self.position];
+        number.parse::<usize>().map(Json::Number).map_err(|_| format!("invalid number {} at position {}", number, start))
+    }
+}
+
+// MAX_PATHS is the amount of paths find_paths keeps per category, two are enough to tell that a path is ambiguous.
+const MAX_PATHS: usize = 2;
+
+// find_paths returns the first MAX_PATHS paths from the last category in `categories` to `to`.
+// The paths from every explored category are kept in `explored`, so each category is only explored once
+// and the search stays linear in the size of the graph even if the amount of paths grows exponentially.
+fn find_paths<'a: 'b, 'b>(
+    graph: &'a CategoryGraph,
+    to: &str,
+    categories: &mut Vec<&'b str>,
+    explored: &mut std::collections::HashMap<&'b str, Vec<Vec<&'a Conversion>>>,
+) -> Result<Vec<Vec<&'a Conversion>>, Box<dyn std::error::Error>> {
+    let current = categories[categories.len() - 1];
+    if current == to {
+        return Ok(vec![Vec::new()]);
+    }
+    // an explored category reached no cycle, otherwise the search would have stopped there
+    if let Some(paths) = explored.get(current) {
+        return Ok(paths.clone());
+    }
+    let mut paths = Vec::new();
+    for conv in graph.get(current).into_iter().flatten() {
+        if let Some(i) = categories.iter().position(|category| *category == conv.to) {
+            let mut cycle = categories[i..].to_vec();
+            cycle.push(&conv.to);
+            return Err(format!("cycle in maps: {}", cycle.join(" -> ")).into());
+        }
+        categories.push(&conv.to);
+        let rest = find_paths(graph, to, categories, explored)?;
+        categories.pop();
+        for path in rest.into_iter().take(MAX_PATHS - paths.len()) {
+            paths.push(std::iter::once(conv).chain(path).collect());
+        }
+    }
+    explored.insert(current, paths.clone());
+    Ok(paths)
+}
+
+// TraceStep describes how a single map converted a value.
+struct TraceStep {
+    map: String,
+    from: usize,
+    to: usize,
+    // the entry used for the conversion, values without an entry map to themselves
+    entry: Option<ConversionEntry>,
+}
+
+impl std::fmt::Display for TraceStep {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match &self.entry {
+            Some(conv) => write!(
+                f, "{}: {} -> {} (entry {} {} {})",
+                self.map, self.from, self.to, conv.destination_range_start, conv.source_range_start, conv.range_length,
+            ),
+            None => write!(f, "{}: {} -> {} (no entry)", self.map, self.from, self.to),
+        }
+    }
+}
+
+// Chain is a path of conversions through the category graph.
+#[derive(Debug)]
+struct Chain<'a> {
+    conversions: Vec<&'a Conversion>,
+}
+
+impl Chain<'_> {
+    fn convert(&self, value: usize) -> usize {
+        self.conversions.iter().fold(value, |current, map| map.lookup(current))
+    }
+
+    fn convert_range(&self, start: usize, length: usize) -> Vec<(usize, usize)> {
+        let mut current = vec![(start, length)];
+        for map in &self.conversions {
+            current = current.into_iter().flat_map(|(start, length)| map.lookup_range(start, length)).collect();
         }
         current
     }
+
+    // trace returns the lineage of a value: one step per map of the chain.
+    fn trace(&self, value: usize) -> Vec<TraceStep> {
+        let mut steps = Vec::new();
+        let mut current = value;
+        for map in &self.conversions {
+            let entry = map.find_entry(current).cloned();
+            let converted = map.lookup(current);
+            steps.push(TraceStep { map: format!("{}-to-{}", map.from, map.to), from: current, to: converted, entry });
+            current = converted;
+        }
+        steps
+    }
+
+    // trace_range returns the ranges covered at every category of the chain, sorted and with adjacent ranges joined.
+    fn trace_range(&self, start: usize, length: usize) -> Vec<(String, Vec<(usize, usize)>)> {
+        let mut stages = Vec::new();
+        let mut current = vec![(start, length)];
+        for map in &self.conversions {
+            current = current.into_iter().flat_map(|(start, length)| map.lookup_range(start, length)).collect();
+            current.sort();
+            let mut joined: Vec<(usize, usize)> = Vec::new();
+            for (start, length) in current {
+                match joined.last_mut() {
+                    Some(last) if last.0 + last.1 >= start => last.1 = std::cmp::max(last.1, start + length - last.0),
+                    _ => joined.push((start, length)),
+                }
+            }
+            current = joined.clone();
+            stages.push((map.to.clone(), joined));
+        }
+        stages
+    }
+
+    // compose flattens the chain into a single conversion.
+    // An empty chain, from a category to itself, has no maps to compose and returns None.
+    fn compose(&self) -> Option<Conversion> {
+        let mut conversions = self.conversions.iter();
+        let mut composed = (*conversions.next()?).clone();
+        for map in conversions {
+            composed = composed.then(map);
+        }
+        Some(composed)
+    }
+}
+
+impl std::fmt::Display for Chain<'_> {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        let mut categories = self.conversions.iter().map(|conv| conv.from.as_str()).collect::<Vec<_>>();
+        if let Some(last) = self.conversions.last() {
+            categories.push(&last.to);
+        }
+        write!(f, "{}", categories.join(" -> "))
+    }
 }
 
 #[cfg(test)]
@@ -253,36 +1082,264 @@ humidity-to-location map:
         let input = read_maps(example_iter()).unwrap();
         assert_eq!(input.seeds, vec![79, 14, 55, 13]);
         assert_eq!(input.maps.len(), 7);
-        let seed_to_soil = input.maps.get("seed").unwrap();
+        let seed_to_soil = &input.maps["seed"][0];
         assert_eq!(seed_to_soil.from, "seed");
         assert_eq!(seed_to_soil.to, "soil");
         assert_eq!(seed_to_soil.map.len(), 2);
         assert_eq!(*seed_to_soil.map.get(&98).unwrap(), ConversionEntry { destination_range_start: 50, source_range_start: 98, range_length: 2 });
         assert_eq!(*seed_to_soil.map.get(&50).unwrap(), ConversionEntry { destination_range_start: 52, source_range_start: 50, range_length: 48 });
-        assert_eq!(input.maps.get("soil").unwrap().map.len(), 3);
-        assert_eq!(input.maps.get("fertilizer").unwrap().map.len(), 4);
-        assert_eq!(input.maps.get("water").unwrap().map.len(), 2);
-        assert_eq!(input.maps.get("light").unwrap().map.len(), 3);
-        assert_eq!(input.maps.get("temperature").unwrap().map.len(), 2);
-        assert_eq!(input.maps.get("humidity").unwrap().map.len(), 2);
+        assert_eq!(input.maps["soil"][0].map.len(), 3);
+        assert_eq!(input.maps["fertilizer"][0].map.len(), 4);
+        assert_eq!(input.maps["water"][0].map.len(), 2);
+        assert_eq!(input.maps["light"][0].map.len(), 3);
+        assert_eq!(input.maps["temperature"][0].map.len(), 2);
+        assert_eq!(input.maps["humidity"][0].map.len(), 2);
     }
 
     #[test]
     fn test_lookup() {
         let input = read_maps(example_iter()).unwrap();
-        assert_eq!(input.maps.get("seed").unwrap().lookup(79), 81);
-        assert_eq!(input.maps.get("seed").unwrap().lookup(14), 14);
-        assert_eq!(input.maps.get("seed").unwrap().lookup(55), 57);
-        assert_eq!(input.maps.get("seed").unwrap().lookup(13), 13);
+        assert_eq!(input.maps["seed"][0].lookup(79), 81);
+        assert_eq!(input.maps["seed"][0].lookup(14), 14);
+        assert_eq!(input.maps["seed"][0].lookup(55), 57);
+        assert_eq!(input.maps["seed"][0].lookup(13), 13);
     }
 
     #[test]
     fn test_convert() {
         let input = read_maps(example_iter()).unwrap();
-        assert_eq!(input.convert(79), 82);
-        assert_eq!(input.convert(14), 43);
-        assert_eq!(input.convert(55), 86);
-        assert_eq!(input.convert(13), 35);
+        let chain = input.chain("seed", "location").unwrap();
+        assert_eq!(chain.convert(79), 82);
+        assert_eq!(chain.convert(14), 43);
+        assert_eq!(chain.convert(55), 86);
+        assert_eq!(chain.convert(13), 35);
+    }
+
+    fn graph_iter(maps: &str) -> impl Iterator<Item = Result<String, io::Error>> {
+        format!("seeds: 1 2\n\n{}", maps).lines().map(|s| Ok(s.to_string())).collect::<Vec<_>>().into_iter()
+    }
+
+    #[test]
+    fn test_chain() {
+        let input = read_maps(example_iter()).unwrap();
+        let chain = input.chain("soil", "humidity").unwrap();
+        assert_eq!(chain.to_string(), "soil -> fertilizer -> water -> light -> temperature -> humidity");
+        assert_eq!(chain.convert(81), 78);
+        assert_eq!(input.reverse_chain("soil", "humidity").unwrap().convert(78), 81);
+        assert_eq!(input.chain("water", "water").unwrap().convert(5), 5);
+        assert_eq!(input.chain("location", "seed").unwrap_err().to_string(), "no path from location to seed");
+        assert_eq!(input.chain("seed", "unknown").unwrap_err().to_string(), "no path from seed to unknown");
+
+        // maps may be given in any order
+        let input = read_maps(graph_iter("b-to-c map:\n10 0 5\n\na-to-b map:\n0 10 5")).unwrap();
+        assert_eq!(input.chain("a", "c").unwrap().convert(12), 12);
+    }
+
+    #[test]
+    fn test_chain_errors() {
+        let input = read_maps(graph_iter("a-to-b map:\n\nb-to-d map:\n\na-to-c map:\n\nc-to-d map:\n")).unwrap();
+        assert_eq!(input.chain("a", "d").unwrap_err().to_string(), "ambiguous paths from a to d: a -> b -> d, a -> c -> d");
+        assert_eq!(input.chain("c", "d").unwrap().to_string(), "c -> d");
+
+        // 40 diamonds in a row have 2^40 paths, only the first two are searched for
+        let diamonds: String = (0..40).map(|i| format!("n{0}-to-a{0} map:\n\na{0}-to-n{1} map:\n\nn{0}-to-b{0} map:\n\nb{0}-to-n{1} map:\n\n", i, i + 1)).collect();
+        let input = read_maps(graph_iter(&diamonds)).unwrap();
+        assert_eq!(input.chain("n38", "n40").unwrap_err().to_string(), "ambiguous paths from n38 to n40: n38 -> a38 -> n39 -> a39 -> n40, n38 -> a38 -> n39 -> b39 -> n40");
+        assert!(input.chain("n0", "n40").unwrap_err().to_string().starts_with("ambiguous paths from n0 to n40: n0 -> a0 -> n1"));
+        assert_eq!(input.chain("n0", "unknown").unwrap_err().to_string(), "no path from n0 to unknown");
+        assert_eq!(input.chain("n39", "a39").unwrap().to_string(), "n39 -> a39");
+
+        let input = read_maps(graph_iter("a-to-b map:\n\nb-to-c map:\n\nc-to-a map:\n\nc-to-d map:\n")).unwrap();
+        assert_eq!(input.chain("a", "d").unwrap_err().to_string(), "cycle in maps: a -> b -> c -> a");
+
+        assert!(read_maps(graph_iter("a-to-b map:\n\na-to-b map:\n")).is_err());
+        assert!(read_maps(graph_iter("a-b map:\n")).is_err());
+    }
+
+    // This crate's own copy of the xorshift generator, almanacs generated from a seed are the same on every run.
+    struct Rng(u64);
+
+    impl Rng {
+        fn next(&mut self) -> u64 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            self.0
+        }
+
+        fn below(&mut self, n: usize) -> usize {
+            (self.next() % n as u64) as usize
+        }
+    }
+
+    // random_almanac generates maps between up to five categories. Entries of a map never overlap.
+    fn random_almanac(rng: &mut Rng) -> Input {
+        let categories = ["seed", "soil", "water", "light", "location"];
+        // seeds repeat now and then, so seed ranges may share their start
+        let mut seeds = Vec::new();
+        for _ in 0..rng.below(6) {
+            let seed = if !seeds.is_empty() && rng.below(3) == 0 { seeds[rng.below(seeds.len())] } else { rng.below(1000) };
+            seeds.push(seed);
+        }
+        let mut maps = Vec::new();
+        for from in 0..categories.len() {
+            for to in 0..categories.len() {
+                if from == to || rng.below(3) != 0 {
+                    continue;
+                }
+                let mut map = Conversion::new(categories[from], categories[to]);
+                let mut source = rng.below(20);
+                for _ in 0..rng.below(6) {
+                    let range_length = rng.below(20);
+                    let destination_range_start = if rng.below(3) == 0 { source } else { rng.below(200) };
+                    map.insert(source, ConversionEntry { destination_range_start, source_range_start: source, range_length });
+                    source += std::cmp::max(range_length, 1) + rng.below(2) * rng.below(20);
+                }
+                maps.push(map);
+            }
+        }
+        build_input(seeds, maps).unwrap()
+    }
+
+    #[test]
+    fn test_format() {
+        let input = read_maps(example_iter()).unwrap();
+        let formatted = input.to_string();
+        assert_eq!(formatted, r#"seeds: 79 14 55 13
+
+seed-to-soil map:
+52 50 48
+50 98 2
+
+soil-to-fertilizer map:
+39 0 15
+0 15 39
+
+fertilizer-to-water map:
+42 0 7
+57 7 4
+0 11 42
+49 53 8
+
+water-to-light map:
+88 18 7
+18 25 70
+
+light-to-temperature map:
+81 45 19
+68 64 13
+45 77 23
+
+temperature-to-humidity map:
+1 0 69
+0 69 1
+
+humidity-to-location map:
+60 56 37
+56 93 4
+"#);
+
+        // adjacent entries are merged and empty entries are dropped
+        let input = read_maps(graph_iter("a-to-b map:\n15 5 5\n10 0 5\n7 20 0")).unwrap();
+        assert_eq!(input.to_string(), "seeds: 1 2\n\na-to-b map:\n10 0 10\n");
+    }
+
+    #[test]
+    fn test_round_trip() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..200 {
+            let input = random_almanac(&mut rng);
+            let text = input.to_string();
+            let from_text = read_maps(text.lines().map(|s| Ok(s.to_string()))).unwrap();
+            let json = input.to_json();
+            let from_json = read_json(&json).unwrap();
+            for parsed in [&from_text, &from_json] {
+                assert_eq!(parsed.to_string(), text);
+                assert_eq!(parsed.to_json(), json);
+                assert_eq!(parsed.seeds, input.seeds);
+                for map in input.maps.values().flatten() {
+                    let parsed_map = parsed.maps[&map.from].iter().find(|parsed_map| parsed_map.to == map.to).unwrap();
+                    assert_eq!(*parsed_map, map.canonical());
+                    for value in 0..250 {
+                        assert_eq!(parsed_map.lookup(value), map.lookup(value));
+                    }
+                }
+            }
+        }
+
+        // read_json accepts exactly the category names that read back the same from the text form
+        const PIECES: [&str; 4] = ["-", "to", "x", "-to"];
+        for _ in 0..1000 {
+            let mut name = || (0..1 + rng.below(4)).map(|_| PIECES[rng.below(PIECES.len())]).collect::<String>();
+            let (from, to) = (name(), name());
+            let json = format!(r#"{{"seeds": [], "maps": [{{"from": "{}", "to": "{}", "entries": []}}]}}"#, from, to);
+            let text = format!("seeds: \n\n{}-to-{} map:\n", from, to);
+            let round_trips = read_maps(text.lines().map(|s| Ok(s.to_string()))).is_ok_and(|input| input.maps.get(&from).is_some_and(|maps| maps[0].to == to));
+            assert_eq!(read_json(&json).is_ok(), round_trips, "{:?} to {:?}", from, to);
+        }
+    }
+
+    #[test]
+    fn test_json() {
+        let input = read_maps(example_iter()).unwrap();
+        let json = input.to_json();
+        assert!(json.starts_with("{\n  \"seeds\": [79, 14, 55, 13],\n  \"maps\": [\n    {\n      \"from\": \"seed\",\n      \"to\": \"soil\",\n      \"entries\": [\n        {\"destination\": 52, \"source\": 50, \"length\": 48},\n"));
+        assert_eq!(read_json(&json).unwrap().to_string(), input.to_string());
+
+        let json = r#"{"maps": [{"to": "b \"x\"\u00e9", "from": "a\\", "entries": []}], "seeds": []}"#;
+        let input = read_json(json).unwrap();
+        assert_eq!(input.to_string(), "seeds: \n\na\\-to-b \"x\"\u{e9} map:\n");
+        assert_eq!(read_json(&input.to_json()).unwrap().to_json(), input.to_json());
+
+        assert!(read_json(r#"{"seeds": [-1], "maps": []}"#).is_err());
+        assert!(read_json(r#"{"seeds": [1], "maps": [}"#).is_err());
+        assert!(read_json(r#"{"seeds": [1]}"#).is_err());
+        assert!(read_json(r#"{"seeds": [], "maps": []} x"#).is_err());
+    }
+
+    #[test]
+    fn test_json_limits() {
+        // deeply nested input is rejected instead of overflowing the stack
+        let nested = format!("{{\"seeds\": {}", "[".repeat(200000));
+        assert_eq!(parse_json(&nested).unwrap_err(), format!("nesting too deep at position {}", "{\"seeds\": ".len() + MAX_JSON_DEPTH - 1));
+        let deepest = format!("{}{}", "[".repeat(MAX_JSON_DEPTH), "]".repeat(MAX_JSON_DEPTH));
+        assert!(parse_json(&deepest).is_ok());
+        assert!(parse_json(&format!("[{}]", deepest)).is_err());
+
+        // \u escapes are exactly four hex digits
+        assert_eq!(parse_json(r#""\u00e9""#), Ok(Json::String("\u{e9}".to_string())));
+        assert!(parse_json(r#""\u+0e9""#).is_err());
+        assert!(parse_json(r#""\u00e""#).is_err());
+        assert!(parse_json("\"\\u00\u{e9}\"").is_err());
+    }
+
+    #[test]
+    fn test_trace() {
+        let input = read_maps(example_iter()).unwrap();
+        let chain = input.chain("seed", "location").unwrap();
+        let steps: Vec<String> = chain.trace(79).iter().map(|step| step.to_string()).collect();
+        assert_eq!(steps, vec![
+            "seed-to-soil: 79 -> 81 (entry 52 50 48)",
+            "soil-to-fertilizer: 81 -> 81 (no entry)",
+            "fertilizer-to-water: 81 -> 81 (no entry)",
+            "water-to-light: 81 -> 74 (entry 18 25 70)",
+            "light-to-temperature: 74 -> 78 (entry 68 64 13)",
+            "temperature-to-humidity: 78 -> 78 (no entry)",
+            "humidity-to-location: 78 -> 82 (entry 60 56 37)",
+        ]);
+        assert_eq!(chain.trace(14).last().unwrap().to, 43);
+    }
+
+    #[test]
+    fn test_trace_range() {
+        let input = read_maps(example_iter()).unwrap();
+        let stages = input.chain("seed", "location").unwrap().trace_range(79, 14);
+        assert_eq!(stages.len(), 7);
+        assert_eq!(stages[0], ("soil".to_string(), vec![(81, 14)]));
+        assert_eq!(stages[4], ("temperature".to_string(), vec![(45, 11), (78, 3)]));
+        assert_eq!(stages[6], ("location".to_string(), vec![(46, 10), (60, 1), (82, 3)]));
+        let covered: usize = stages.iter().map(|(_, ranges)| ranges.iter().map(|range| range.1).sum::<usize>()).sum();
+        assert_eq!(covered, 7 * 14);
     }
 
     #[test]
@@ -296,6 +1353,256 @@ humidity-to-location map:
         let input = read_maps(example_iter()).unwrap();
         assert_eq!(part_2_brute_forward(&input).unwrap(), 46);
         assert_eq!(part_2_brute_backward(&input).unwrap(), 46);
+        assert_eq!(part_2(&input).unwrap(), 46);
+
+        // both seed ranges start at 5, only the longer one contains seed 6
+        let input = read_maps("seeds: 5 10 5 1\n\nseed-to-location map:\n100 5 1".lines().map(|s| Ok(s.to_string()))).unwrap();
+        assert_eq!(part_2(&input).unwrap(), 6);
+        assert_eq!(part_2_brute_forward(&input).unwrap(), 6);
+    }
+
+    #[test]
+    fn test_lookup_range() {
+        let input = read_maps(example_iter()).unwrap();
+        let seed_to_soil = &input.maps["seed"][0];
+        assert_eq!(seed_to_soil.lookup_range(0, 10), vec![(0, 10)]);
+        assert_eq!(seed_to_soil.lookup_range(45, 10), vec![(45, 5), (52, 5)]);
+        assert_eq!(seed_to_soil.lookup_range(97, 2), vec![(99, 1), (50, 1)]);
+        assert_eq!(seed_to_soil.lookup_range(95, 10), vec![(97, 3), (50, 2), (100, 5)]);
+        assert_eq!(seed_to_soil.lookup_range(50, 0), vec![]);
+    }
+
+    #[test]
+    fn test_compose() {
+        let input = read_maps(example_iter()).unwrap();
+        let chain = input.chain("seed", "location").unwrap();
+        let composed = chain.compose().unwrap();
+        assert_eq!(composed.from, "seed");
+        assert_eq!(composed.to, "location");
+        for seed in &input.seeds {
+            assert_eq!(composed.lookup(*seed), chain.convert(*seed));
+        }
+        let lowest_location = input.seed_ranges.iter()
+            .flat_map(|(start, length)| composed.lookup_range(*start, *length))
+            .map(|range| range.0)
+            .min();
+        assert_eq!(lowest_location, Some(46));
+        assert!(input.chain("water", "water").unwrap().compose().is_none());
+    }
+
+    #[test]
+    fn test_compose_export() {
+        let input = read_maps(example_iter()).unwrap();
+        let seed_to_fertilizer = input.maps["seed"][0].then(&input.maps["soil"][0]);
+        let exported = seed_to_fertilizer.to_string();
+        assert_eq!(exported, "seed-to-fertilizer map:\n39 0 15\n0 15 35\n37 50 2\n54 52 46\n35 98 2\n");
+        let (parsed, _) = read_map(exported.lines().map(|s| Ok(s.to_string()))).unwrap().unwrap();
+        assert_eq!(parsed.map, seed_to_fertilizer.map);
+    }
+
+    #[test]
+    fn test_convert_range_matches_brute_force() {
+        let input = read_maps(example_iter()).unwrap();
+        let chain = input.chain("seed", "location").unwrap();
+        for start in 0..110 {
+            for length in 1..12 {
+                let brute_force = (start..start + length).map(|seed| chain.convert(seed)).min().unwrap();
+                let ranges = chain.convert_range(start, length);
+                assert_eq!(ranges.iter().map(|range| range.1).sum::<usize>(), length);
+                assert_eq!(ranges.iter().map(|range| range.0).min().unwrap(), brute_force, "range {}+{}", start, length);
+            }
+        }
+        let composed = chain.compose().unwrap();
+        for seed in 0..110 {
+            assert_eq!(composed.lookup(seed), chain.convert(seed));
+        }
     }
 
-}
\ No newline at end of file
+    #[test]
+    fn test_part_2_parallel() {
+        let input = read_maps(example_iter()).unwrap();
+        for workers in [0, 1, 2, 8] {
+            assert_eq!(part_2_brute_forward_parallel(&input, workers).unwrap(), 46);
+        }
+
+        // a single seed range that spans several chunks
+        let seeds = format!("seeds: 0 {}", 3 * BRUTE_FORCE_CHUNK_SIZE + 7);
+        let input = read_maps(format!("{}\n\nseed-to-location map:\n5 {} 10", seeds, 2 * BRUTE_FORCE_CHUNK_SIZE).lines().map(|s| Ok(s.to_string()))).unwrap();
+        assert_eq!(part_2_brute_forward_parallel(&input, 4).unwrap(), 0);
+
+        // empty ranges have no chunks, the others end in a partial chunk
+        let seeds = format!("seeds: 9 0 {} {} 3 1 7 0", BRUTE_FORCE_CHUNK_SIZE, 2 * BRUTE_FORCE_CHUNK_SIZE + 1);
+        let input = read_maps(format!("{}\n\nseed-to-location map:\n0 {} 1", seeds, 3 * BRUTE_FORCE_CHUNK_SIZE).lines().map(|s| Ok(s.to_string()))).unwrap();
+        for workers in [1, 3] {
+            assert_eq!(part_2_brute_forward_parallel(&input, workers).unwrap(), 0);
+        }
+        assert_eq!(part_2_brute_forward(&input).unwrap(), 0);
+
+        // chunks are not stored, but their indexes still have to fit into a usize
+        let input = build_input([0, usize::MAX].repeat(1 << 16), vec![Conversion::new("seed", "location")]).unwrap();
+        assert_eq!(part_2_brute_forward_parallel(&input, 2).unwrap_err().to_string(), "too many seeds to brute force");
+
+        let mut rng = Rng(0xc0ffee);
+        let mut checked = 0;
+        while checked < 50 {
+            let input = random_almanac(&mut rng);
+            if input.chain("seed", "location").is_err() || input.seeds.len() < 2 {
+                continue;
+            }
+            assert_eq!(part_2_brute_forward_parallel(&input, 3).unwrap(), part_2_brute_forward(&input).unwrap());
+            assert_eq!(part_2_brute_forward_parallel(&input, 3).unwrap(), part_2(&input).unwrap());
+            checked += 1;
+        }
+    }
+
+    #[test]
+    fn test_range_boundaries() {
+        let input = read_maps(example_iter()).unwrap();
+        let seed_to_soil = &input.maps["seed"][0];
+        // 52 50 48 covers 50..=97 and 50 98 2 covers 98..=99
+        assert_eq!(seed_to_soil.lookup(49), 49);
+        assert_eq!(seed_to_soil.lookup(50), 52);
+        assert_eq!(seed_to_soil.lookup(97), 99);
+        assert_eq!(seed_to_soil.lookup(98), 50);
+        assert_eq!(seed_to_soil.lookup(99), 51);
+        assert_eq!(seed_to_soil.lookup(100), 100);
+        let soil_to_fertilizer = &input.maps["soil"][0];
+        // 37 52 2 covers 52..=53
+        assert_eq!(soil_to_fertilizer.lookup(53), 38);
+        assert_eq!(soil_to_fertilizer.lookup(54), 54);
+
+        // seed ranges are 79..=92 and 55..=67
+        assert!(!input.seed_in_range(54));
+        assert!(input.seed_in_range(55));
+        assert!(input.seed_in_range(67));
+        assert!(!input.seed_in_range(68));
+        assert!(input.seed_in_range(79));
+        assert!(input.seed_in_range(92));
+        assert!(!input.seed_in_range(93));
+
+        // seed ranges 0..=9 and 5..=5 overlap, 6..=9 are only covered by the first one
+        let text = "seeds: 0 10 5 1\n\nseed-to-location map:\n100 0 7\n0 100 7";
+        let input = read_maps(text.lines().map(|s| Ok(s.to_string()))).unwrap();
+        assert!(input.seed_in_range(5));
+        assert!(input.seed_in_range(6));
+        assert!(input.seed_in_range(9));
+        assert!(!input.seed_in_range(10));
+        assert_eq!(part_2_brute_backward(&input).unwrap(), 7);
+        assert_eq!(part_2_brute_forward(&input).unwrap(), 7);
+    }
+
+    #[test]
+    fn test_ranges_up_to_usize_max() {
+        let max = usize::MAX;
+        let read = |text: String| read_maps(text.lines().map(|s| Ok(s.to_string())).collect::<Vec<_>>().into_iter());
+        assert_eq!(
+            read(format!("seeds: 1 2\n\nseed-to-location map:\n{} 0 5", max)).err().map(|e| e.to_string()),
+            Some(format!("invalid map: entry {} 0 5 reaches past {} in seed-to-location map", max, max)),
+        );
+        assert!(read(format!("seeds: 1 2\n\nseed-to-location map:\n0 {} 5", max)).is_err());
+        assert!(read(format!("seeds: 1 2\n\nseed-to-location map:\n0 1 {}", max)).is_err());
+        assert_eq!(
+            read(format!("seeds: 5 {}\n\nseed-to-location map:\n", max)).err().map(|e| e.to_string()),
+            Some(format!("invalid input: seed range 5 {} reaches past {}", max, max)),
+        );
+        let json = format!(r#"{{"seeds": [], "maps": [{{"from": "a", "to": "b", "entries": [{{"destination": 0, "source": {}, "length": 1}}]}}]}}"#, max);
+        assert!(read_json(&json).is_err());
+
+        // ranges ending right at usize::MAX are fine
+        let input = read(format!("seeds: {} 5\n\nseed-to-location map:\n5 {} 5\n0 0 3", max - 5, max - 5)).unwrap();
+        assert_eq!(part_1(&input).unwrap(), 5);
+        assert_eq!(part_2(&input).unwrap(), 5);
+        assert_eq!(part_2_brute_forward_parallel(&input, 2).unwrap(), 5);
+        assert_eq!(input.chain("seed", "location").unwrap().convert(max - 1), 9);
+    }
+
+    #[test]
+    fn test_part_2_first_seed_of_range() {
+        // the lowest location belongs to the first seed of the range
+        let input = read_maps(graph_iter("seed-to-location map:\n10 0 5\n0 10 5")).unwrap();
+        assert_eq!(part_2(&input).unwrap(), 11);
+        assert_eq!(part_2_brute_forward(&input).unwrap(), 11);
+        assert_eq!(part_2_brute_backward(&input).unwrap(), 11);
+    }
+
+    #[test]
+    fn test_validate() {
+        let input = read_maps(example_iter()).unwrap();
+        assert_eq!(input.validate(), vec![]);
+
+        let input = read_maps(graph_iter("a-to-b map:\n0 0 10\n10 5 10\n20 20 5")).unwrap();
+        let issues: Vec<String> = input.validate().iter().map(|issue| issue.to_string()).collect();
+        assert_eq!(issues, vec![
+            "a-to-b: source ranges 0..10 and 5..15 overlap",
+            "a-to-b: gap 15..20 maps to itself",
+            "a-to-b: source ranges 5..15 and 15..20 both map to 15..20",
+        ]);
+
+        let input = read_maps(graph_iter("a-to-b map:\n5 0 5\n0 5 5\n\nb-to-c map:\n0 0 5\n2 5 5")).unwrap();
+        assert_eq!(input.validate(), vec![
+            Issue::OverlappingDestinations { map: "b-to-c".to_string(), first: (0, 5), second: (5, 5), destination: (2, 3) },
+        ]);
+        assert!(input.reverse_chain("a", "b").is_ok());
+        assert_eq!(input.reverse_chain("a", "c").unwrap_err().to_string(), "b-to-c map is not invertible");
+        assert!(read_maps(graph_iter("a-to-b map:\n1 0 1\n2 0 1")).is_err());
+    }
+
+
+    // chained_almanac leads from seed to location through up to three more categories, one map per step. Entries
+    // never overlap, with identity every entry maps its range onto itself.
+    fn chained_almanac(rng: &mut Rng, identity: bool) -> Input {
+        let mut categories = vec!["seed".to_string()];
+        for i in 0..rng.below(4) {
+            categories.push(format!("category{}", i));
+        }
+        categories.push("location".to_string());
+        let seeds: Vec<usize> = (0..2 * (1 + rng.below(4))).map(|i| if i % 2 == 0 { rng.below(1000) } else { rng.below(200) }).collect();
+        let mut maps = Vec::new();
+        for pair in categories.windows(2) {
+            let mut map = Conversion::new(&pair[0], &pair[1]);
+            let mut source = rng.below(50);
+            for _ in 0..rng.below(5) {
+                let range_length = 1 + rng.below(100);
+                let destination_range_start = if identity { source } else { rng.below(1000) };
+                map.insert(source, ConversionEntry { destination_range_start, source_range_start: source, range_length });
+                source += range_length + rng.below(50);
+            }
+            maps.push(map);
+        }
+        build_input(seeds, maps).unwrap()
+    }
+
+    #[test]
+    fn test_identity_almanac_maps_seeds_to_themselves() {
+        let mut rng = Rng(0x1d);
+        for _ in 0..200 {
+            let generated = chained_almanac(&mut rng, true);
+            // read the almanac back from its text like a puzzle input
+            let input = read_maps(generated.to_string().lines().map(|line| Ok(line.to_string()))).unwrap();
+            assert!(input.validate().iter().all(|issue| !issue.is_error()));
+            assert_eq!(part_1(&input).unwrap(), *input.seeds.iter().min().unwrap());
+            match input.seed_ranges.iter().filter(|(_, length)| *length > 0).map(|(start, _)| *start).min() {
+                Some(start) => assert_eq!(part_2(&input).unwrap(), start),
+                None => assert!(part_2(&input).is_err()),
+            }
+            let chain = input.chain("seed", "location").unwrap();
+            for _ in 0..20 {
+                let value = rng.below(2000);
+                assert_eq!(chain.convert(value), value);
+            }
+        }
+    }
+
+    #[test]
+    fn test_random_almanacs_match_brute_force() {
+        let mut rng = Rng(0xa1a);
+        for _ in 0..200 {
+            let input = chained_almanac(&mut rng, false);
+            let chain = input.chain("seed", "location").unwrap();
+            let smallest_seed_location = input.seeds.iter().map(|seed| chain.convert(*seed)).min();
+            assert_eq!(part_1(&input).ok(), smallest_seed_location);
+            let brute_force = input.seed_ranges.iter().flat_map(|(start, length)| *start..start + length).map(|seed| chain.convert(seed)).min();
+            assert_eq!(part_2(&input).ok(), brute_force, "\n{}", input);
+        }
+    }
+}
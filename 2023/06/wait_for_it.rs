@@ -1,69 +1,659 @@
 use std::io::{self, BufRead};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
-    let stdin = io::stdin();
-    let lines = stdin.lock().lines();
+    let args: Vec<String> = std::env::args().collect();
+    // --separate reads every column as its own race, --joined reads all digits of a row as a single race.
+    // Without a flag (or with both) both readings of the sheet are reported.
+    let separate = args.contains(&"--separate".to_string());
+    let joined = args.contains(&"--joined".to_string());
+    let modes = match (separate, joined) {
+        (true, false) => vec![Kerning::Separate],
+        (false, true) => vec![Kerning::Joined],
+        _ => vec![Kerning::Separate, Kerning::Joined],
+    };
+
+    let physics = read_physics(&args)?;
+    let check = args.contains(&"--check".to_string());
 
-    let input = read_race_part2(lines)?;
+    let stdin = io::stdin();
+    let lines: Vec<String> = stdin.lock().lines().collect::<Result<_, _>>()?;
 
-    let wins = calculate_number_of_wins_multiplied(&input);
-    println!("number of wins multiplied: {}", wins);
+    for mode in modes {
+        let races = read_races(lines.iter().map(String::as_str), mode)?;
+        if check {
+            for &(time_limit, current_best_distance) in &races {
+                let exact = physics.count_wins(current_best_distance, time_limit).ok_or("distance is too large")?;
+                let reference = physics.count_wins_brute_force(current_best_distance, time_limit).ok_or("distance is too large")?;
+                if exact != reference {
+                    return Err(format!("race {} ms / {} mm: exact count {} but brute force counts {}", time_limit, current_best_distance, exact, reference).into());
+                }
+            }
+            eprintln!("{}: exact counts match the brute force reference", mode);
+        }
+        let wins = calculate_number_of_wins_multiplied(&races, &physics).ok_or("number of wins is too large")?;
+        println!("{} ({} races): number of wins multiplied: {}", mode, races.len(), wins);
+    }
 
     Ok(())
 }
 
-fn winning_range(current_best_distance: i64, time_limit: i64) -> i64 {
-    let t = time_limit as f64;
-    let d = current_best_distance as f64;
-    let x1 = f64::ceil((t + f64::sqrt(t.powf(2.0) - 4.0 * d)) / 2.0);
-    let x2 = f64::floor((t - f64::sqrt(t.powf(2.0) - 4.0 * d)) / 2.0) + 1.0;
-    return (x1 - x2) as i64;
+// Kerning selects how the digits on the sheet are read.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Kerning {
+    // every column is its own race (part 1)
+    Separate,
+    // the spaces are bad kerning and all digits form a single race (part 2)
+    Joined,
 }
 
-fn read_number_row(line: &str) -> Result<Vec<i64>, Box<dyn std::error::Error>> {
-    let mut numbers = Vec::new();
-    let line = line.split_once(":").ok_or("no : found")?.1;
-    for number in line.split(" ") {
-        if number == "" {
-            continue;
+impl std::fmt::Display for Kerning {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Kerning::Separate => write!(f, "separate races"),
+            Kerning::Joined => write!(f, "joined race"),
         }
-        numbers.push(number.parse::<i64>()?);
     }
-    Ok(numbers)
 }
 
-fn read_single_number_row(line: &str) -> Result<i64, Box<dyn std::error::Error>> {
-    let line = line.split_once(":").ok_or("no : found")?.1;
-    let cleaned_string = line.replace(" ", "");
-    cleaned_string.parse::<i64>().map_err(|e| e.into())
+// winning_range counts the button hold times h in [0, time_limit] with h * (time_limit - h) > current_best_distance.
+// The bounds are the roots of h^2 - time_limit * h + current_best_distance = 0. They are computed with an integer square root
+// and corrected to the first hold time that wins, so there is no loss of precision for large inputs.
+// Returns None if time_limit^2 does not fit into an i128.
+fn winning_range(current_best_distance: i128, time_limit: i128) -> Option<i128> {
+    if time_limit < 0 {
+        return Some(0);
+    }
+    let discriminant = time_limit.checked_mul(time_limit)?.checked_sub(current_best_distance.checked_mul(4)?)?;
+    if discriminant <= 0 {
+        // the best possible distance (time_limit / 2)^2 does not beat the record
+        return Some(0);
+    }
+    let wins = |hold: i128| hold * (time_limit - hold) > current_best_distance;
+    let root = isqrt(discriminant as u128) as i128;
+    let mut lowest = std::cmp::max((time_limit - root) / 2 - 1, 0);
+    while lowest <= time_limit / 2 && !wins(lowest) {
+        lowest += 1;
+    }
+    while lowest > 0 && wins(lowest - 1) {
+        lowest -= 1;
+    }
+    // the distance is symmetric around time_limit / 2, so the highest winning hold time is time_limit - lowest
+    Some(std::cmp::max(time_limit - 2 * lowest + 1, 0))
 }
 
-#[allow(dead_code)]
-fn read_races_part1(mut lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<Vec<(i64, i64)>, Box<dyn std::error::Error>> {
-    let times = read_number_row(&lines.next().ok_or("no first line")??)?;
-    let distances = read_number_row(&lines.next().ok_or("no second line")??)?;
-    if times.len() != distances.len() {
-        return Err("times and distances are not the same length".into());
+// isqrt returns the floor of the square root of n using Newton's method.
+fn isqrt(n: u128) -> u128 {
+    if n < 2 {
+        return n;
     }
+    // start above the root, every step then moves down towards it
+    let mut x: u128 = 1 << (128 - n.leading_zeros()).div_ceil(2);
+    loop {
+        let y = (x + n / x) / 2;
+        if y >= x {
+            return x;
+        }
+        x = y;
+    }
+}
 
-    let mut combined = Vec::new();
-    for (time, distance) in times.iter().zip(distances.iter()) {
-        combined.push((*time, *distance));
+// Stage charges the boat for duration milliseconds, every millisecond adding acceleration to the speed.
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Stage {
+    // None charges until the button is released
+    duration: Option<i128>,
+    acceleration: i128,
+}
+
+// Physics describes how holding the button turns into distance.
+// Holding for h milliseconds charges the speed through the stages in order, the speed stays put once all stages are used up.
+// The speed never exceeds max_speed and the boat only starts moving delay milliseconds after the button is released.
+#[derive(Clone, Debug, PartialEq)]
+struct Physics {
+    stages: Vec<Stage>,
+    max_speed: Option<i128>,
+    delay: i128,
+}
+
+// Segment is a run of hold times start..=end over which the speed is base + acceleration * (h - start).
+#[derive(Clone, Copy, Debug, PartialEq)]
+struct Segment {
+    start: i128,
+    end: i128,
+    base: i128,
+    acceleration: i128,
+}
+
+impl Physics {
+    // standard is the puzzle's boat: one millimeter per millisecond of speed for every millisecond of holding
+    fn standard() -> Physics {
+        Physics::accelerating(1)
+    }
+
+    fn accelerating(acceleration: i128) -> Physics {
+        Physics {
+            stages: vec![Stage { duration: None, acceleration }],
+            max_speed: None,
+            delay: 0,
+        }
     }
 
-    Ok(combined)
+    fn validate(&self) -> Result<(), String> {
+        if self.stages.is_empty() {
+            return Err("at least one charging stage is needed".to_string());
+        }
+        for (i, stage) in self.stages.iter().enumerate() {
+            if stage.acceleration < 0 {
+                return Err(format!("stage {} has negative acceleration {}", i + 1, stage.acceleration));
+            }
+            match stage.duration {
+                Some(duration) if duration <= 0 => return Err(format!("stage {} has non-positive duration {}", i + 1, duration)),
+                None if i + 1 != self.stages.len() => return Err(format!("only the last stage may charge without limit, stage {} does", i + 1)),
+                _ => {}
+            }
+        }
+        if let Some(max_speed) = self.max_speed {
+            if max_speed < 0 {
+                return Err(format!("negative maximum speed {}", max_speed));
+            }
+        }
+        if self.delay < 0 {
+            return Err(format!("negative startup delay {}", self.delay));
+        }
+        Ok(())
+    }
+
+    // segments splits the hold times 0..=time_limit at every stage boundary and where the speed cap is reached.
+    fn segments(&self, time_limit: i128) -> Option<Vec<Segment>> {
+        let exhausted = Stage { duration: None, acceleration: 0 };
+        let mut segments = Vec::new();
+        let mut start = 0;
+        let mut base: i128 = 0;
+        for stage in self.stages.iter().chain(std::iter::once(&exhausted)) {
+            if start > time_limit {
+                break;
+            }
+            let end = match stage.duration {
+                Some(duration) => std::cmp::min(start.checked_add(duration)? - 1, time_limit),
+                None => time_limit,
+            };
+            let segment = Segment { start, end, base, acceleration: stage.acceleration };
+            match self.max_speed {
+                Some(max_speed) if base >= max_speed => segments.push(Segment { base: max_speed, acceleration: 0, ..segment }),
+                Some(max_speed) if stage.acceleration > 0 => {
+                    // first hold time of the stage at which the speed reaches the cap
+                    let capped = start + (max_speed - base + stage.acceleration - 1) / stage.acceleration;
+                    if capped <= end {
+                        segments.push(Segment { end: capped - 1, ..segment });
+                        segments.push(Segment { start: capped, end, base: max_speed, acceleration: 0 });
+                    } else {
+                        segments.push(segment);
+                    }
+                }
+                _ => segments.push(segment),
+            }
+            match stage.duration {
+                Some(duration) => {
+                    base = base.checked_add(stage.acceleration.checked_mul(duration)?)?;
+                    start = start.checked_add(duration)?;
+                }
+                None => break,
+            }
+        }
+        Some(segments)
+    }
+
+    fn speed(&self, hold: i128) -> Option<i128> {
+        let mut speed: i128 = 0;
+        let mut remaining = hold;
+        for stage in &self.stages {
+            let charged = match stage.duration {
+                Some(duration) => std::cmp::min(duration, remaining),
+                None => remaining,
+            };
+            speed = speed.checked_add(stage.acceleration.checked_mul(charged)?)?;
+            remaining -= charged;
+            if remaining == 0 {
+                break;
+            }
+        }
+        Some(match self.max_speed {
+            Some(max_speed) => std::cmp::min(speed, max_speed),
+            None => speed,
+        })
+    }
+
+    // distance returns how far the boat travels, None if it does not fit into an i128
+    fn distance(&self, hold: i128, time_limit: i128) -> Option<i128> {
+        let moving = time_limit - hold - self.delay;
+        if moving <= 0 {
+            return Some(0);
+        }
+        self.speed(hold)?.checked_mul(moving)
+    }
+
+    // count_wins counts the hold times in 0..=time_limit that beat current_best_distance.
+    // Returns None if a distance does not fit into an i128.
+    fn count_wins(&self, current_best_distance: i128, time_limit: i128) -> Option<i128> {
+        if time_limit < 0 {
+            return Some(0);
+        }
+        if let [Stage { duration: None, acceleration }] = self.stages[..] {
+            if acceleration > 0 && self.max_speed.is_none() && self.delay == 0 {
+                // acceleration * x > d holds exactly for x > floor(d / acceleration)
+                return winning_range(current_best_distance.div_euclid(acceleration), time_limit);
+            }
+        }
+        // past this hold time the boat does not move any more
+        let last_moving = time_limit - self.delay - 1;
+        let mut wins = 0;
+        for segment in self.segments(time_limit)? {
+            let moving_end = std::cmp::min(segment.end, last_moving);
+            if segment.start <= moving_end {
+                wins += segment.count_wins(moving_end, current_best_distance, time_limit - self.delay)?;
+            }
+            if current_best_distance < 0 {
+                // standing still still beats a negative record
+                wins += segment.end - std::cmp::max(segment.start, moving_end + 1) + 1;
+            }
+        }
+        Some(wins)
+    }
+
+    // count_wins_brute_force is the reference for count_wins and tries every hold time
+    fn count_wins_brute_force(&self, current_best_distance: i128, time_limit: i128) -> Option<i128> {
+        let mut wins = 0;
+        for hold in 0..=time_limit {
+            if self.distance(hold, time_limit)? > current_best_distance {
+                wins += 1;
+            }
+        }
+        Some(wins)
+    }
+}
+
+impl Segment {
+    // count_wins counts the hold times in start..=end with (base + acceleration * (h - start)) * (moving_time - h) > current_best_distance.
+    // The distance is a concave parabola in h, so it rises up to a peak and falls afterwards and both bounds are found by binary search.
+    fn count_wins(&self, end: i128, current_best_distance: i128, moving_time: i128) -> Option<i128> {
+        let distance = |hold: i128| (self.base.checked_add(self.acceleration.checked_mul(hold - self.start)?)?).checked_mul(moving_time - hold);
+        // the first hold time that is not followed by a longer distance
+        let mut low = self.start;
+        let mut high = end;
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if distance(mid + 1)? <= distance(mid)? {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        let peak = low;
+        if distance(peak)? <= current_best_distance {
+            return Some(0);
+        }
+        // first winning hold time on the rising side
+        let (mut low, mut high) = (self.start, peak);
+        while low < high {
+            let mid = low + (high - low) / 2;
+            if distance(mid)? > current_best_distance {
+                high = mid;
+            } else {
+                low = mid + 1;
+            }
+        }
+        let first = low;
+        // last winning hold time on the falling side
+        let (mut low, mut high) = (peak, end);
+        while low < high {
+            let mid = low + (high - low + 1) / 2;
+            if distance(mid)? > current_best_distance {
+                low = mid;
+            } else {
+                high = mid - 1;
+            }
+        }
+        Some(low - first + 1)
+    }
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1)).map(String::as_str)
+}
+
+// read_physics builds the race physics from --acceleration, --stages, --max-speed and --delay.
+// Stages are given as duration:acceleration pairs separated by commas, the last one may omit the duration, e.g. 5:1,10:2,3.
+fn read_physics(args: &[String]) -> Result<Physics, Box<dyn std::error::Error>> {
+    let mut physics = match (flag_value(args, "--acceleration"), flag_value(args, "--stages")) {
+        (Some(_), Some(_)) => return Err("--acceleration and --stages cannot be combined".into()),
+        (Some(acceleration), None) => Physics::accelerating(acceleration.parse()?),
+        (None, Some(stages)) => {
+            let mut physics = Physics::standard();
+            physics.stages.clear();
+            for stage in stages.split(',') {
+                physics.stages.push(match stage.split_once(':') {
+                    Some((duration, acceleration)) => Stage { duration: Some(duration.trim().parse()?), acceleration: acceleration.trim().parse()? },
+                    None => Stage { duration: None, acceleration: stage.trim().parse()? },
+                });
+            }
+            physics
+        }
+        (None, None) => Physics::standard(),
+    };
+    if let Some(max_speed) = flag_value(args, "--max-speed") {
+        physics.max_speed = Some(max_speed.parse()?);
+    }
+    if let Some(delay) = flag_value(args, "--delay") {
+        physics.delay = delay.parse()?;
+    }
+    physics.validate()?;
+    Ok(physics)
 }
 
-fn read_race_part2(mut lines: impl Iterator<Item = Result<String, io::Error>>) -> Result<Vec<(i64, i64)>, Box<dyn std::error::Error>> {
-    let time = read_single_number_row(&lines.next().ok_or("no first line")??)?;
-    let distance = read_single_number_row(&lines.next().ok_or("no second line")??)?;
-    Ok(vec![(time, distance)])
+// read_labeled_row returns the text after "<label>:" and fails if the line carries a different label.
+fn read_labeled_row<'a>(line: &'a str, label: &str) -> Result<&'a str, Box<dyn std::error::Error>> {
+    let (found, rest) = line.split_once(':').ok_or_else(|| format!("no : found in line {:?}", line))?;
+    if found.trim() != label {
+        return Err(format!("expected label {:?}, found {:?}", label, found.trim()).into());
+    }
+    Ok(rest)
 }
 
-fn calculate_number_of_wins_multiplied(input: &[(i64, i64)]) -> i64 {
-    let mut wins = 1;
+fn read_number_row(line: &str, label: &str, kerning: Kerning) -> Result<Vec<i128>, Box<dyn std::error::Error>> {
+    let line = read_labeled_row(line, label)?;
+    let mut numbers = Vec::new();
+    match kerning {
+        Kerning::Separate => {
+            for number in line.split_whitespace() {
+                numbers.push(number.parse::<i128>()?);
+            }
+        }
+        Kerning::Joined => {
+            let cleaned_string: String = line.split_whitespace().collect();
+            numbers.push(cleaned_string.parse::<i128>()?);
+        }
+    }
+    Ok(numbers)
+}
+
+// read_races reads the Time and Distance rows into (time, distance) pairs.
+fn read_races<'a>(mut lines: impl Iterator<Item = &'a str>, kerning: Kerning) -> Result<Vec<(i128, i128)>, Box<dyn std::error::Error>> {
+    let times = read_number_row(lines.next().ok_or("no first line")?, "Time", kerning)?;
+    let distances = read_number_row(lines.next().ok_or("no second line")?, "Distance", kerning)?;
+    if times.len() != distances.len() {
+        return Err("times and distances are not the same length".into());
+    }
+    if let Some(line) = lines.find(|line| !line.trim().is_empty()) {
+        return Err(format!("unexpected line after the distances: {:?}", line).into());
+    }
+
+    Ok(times.into_iter().zip(distances).collect())
+}
+
+fn calculate_number_of_wins_multiplied(input: &[(i128, i128)], physics: &Physics) -> Option<i128> {
+    let mut wins: i128 = 1;
     for game in input {
-        wins *= winning_range(game.1, game.0);
+        wins = wins.checked_mul(physics.count_wins(game.1, game.0)?)?;
+    }
+    Some(wins)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn brute_force(current_best_distance: i128, time_limit: i128) -> i128 {
+        (0..=time_limit).filter(|hold| hold * (time_limit - hold) > current_best_distance).count() as i128
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(winning_range(9, 7), Some(4));
+        assert_eq!(winning_range(40, 15), Some(8));
+        assert_eq!(winning_range(200, 30), Some(9));
+        assert_eq!(calculate_number_of_wins_multiplied(&[(7, 9), (15, 40), (30, 200)], &Physics::standard()), Some(288));
+        assert_eq!(winning_range(940200, 71530), Some(71503));
+    }
+
+    const EXAMPLE: &str = "Time:      7  15   30\nDistance:  9  40  200\n";
+
+    #[test]
+    fn test_read_races() {
+        let separate = read_races(EXAMPLE.lines(), Kerning::Separate).unwrap();
+        assert_eq!(separate, vec![(7, 9), (15, 40), (30, 200)]);
+        assert_eq!(calculate_number_of_wins_multiplied(&separate, &Physics::standard()), Some(288));
+        let joined = read_races(EXAMPLE.lines(), Kerning::Joined).unwrap();
+        assert_eq!(joined, vec![(71530, 940200)]);
+        assert_eq!(calculate_number_of_wins_multiplied(&joined, &Physics::standard()), Some(71503));
+
+        let input = include_str!("input.txt");
+        let separate = read_races(input.lines(), Kerning::Separate).unwrap();
+        assert_eq!(calculate_number_of_wins_multiplied(&separate, &Physics::standard()), Some(128700));
+        let joined = read_races(input.lines(), Kerning::Joined).unwrap();
+        assert_eq!(calculate_number_of_wins_multiplied(&joined, &Physics::standard()), Some(39594072));
+    }
+
+    #[test]
+    fn test_read_races_labels() {
+        let swapped = "Distance:  9  40  200\nTime:      7  15   30\n";
+        assert!(read_races(swapped.lines(), Kerning::Separate).unwrap_err().to_string().contains("expected label \"Time\""));
+        let typo = "Time:      7  15   30\nDistanse:  9  40  200\n";
+        assert!(read_races(typo.lines(), Kerning::Joined).is_err());
+        assert!(read_races("Time 7\nDistance 9".lines(), Kerning::Separate).is_err());
+        assert!(read_races("Time: 7 15\nDistance: 9".lines(), Kerning::Separate).is_err());
+        assert!(read_races("Time: 7\n".lines(), Kerning::Separate).is_err());
+        assert!(read_races("Time: 7\nDistance: 9\nTime: 8".lines(), Kerning::Separate).is_err());
+        assert!(read_races("Time: 7 x\nDistance: 9 1".lines(), Kerning::Separate).is_err());
+    }
+
+    // Like the other days, wait_for_it has its own xorshift generator, so every run checks the same races.
+    struct Rng(u64);
+
+    impl Rng {
+        fn below(&mut self, n: i128) -> i128 {
+            self.0 ^= self.0 << 13;
+            self.0 ^= self.0 >> 7;
+            self.0 ^= self.0 << 17;
+            (self.0 as i128).rem_euclid(n)
+        }
+    }
+
+    #[test]
+    fn test_winning_range_matches_brute_force() {
+        let mut rng = Rng(0x5eed);
+        for _ in 0..2000 {
+            let time_limit = rng.below(200);
+            let best = time_limit * time_limit / 4;
+            let current_best_distance = rng.below(best + 10) - 5;
+            assert_eq!(winning_range(current_best_distance, time_limit), Some(brute_force(current_best_distance, time_limit)));
+        }
+        for time_limit in 0..60 {
+            for current_best_distance in -3..time_limit * time_limit / 4 + 3 {
+                assert_eq!(
+                    winning_range(current_best_distance, time_limit),
+                    Some(brute_force(current_best_distance, time_limit)),
+                    "time {} distance {}", time_limit, current_best_distance,
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn test_winning_range_edges() {
+        // perfect square discriminants: the roots themselves only tie the record
+        assert_eq!(winning_range(6, 5), Some(0));
+        assert_eq!(winning_range(4, 5), Some(2));
+        assert_eq!(winning_range(16, 8), Some(0));
+        assert_eq!(winning_range(15, 8), Some(1));
+        // negative discriminant
+        assert_eq!(winning_range(100, 10), Some(0));
+        assert_eq!(winning_range(0, 0), Some(0));
+        assert_eq!(winning_range(-1, 0), Some(1));
+        assert_eq!(winning_range(5, -3), Some(0));
+        assert_eq!(winning_range(0, i128::MAX), None);
+    }
+
+    #[test]
+    fn test_winning_range_large() {
+        // with distance (t/2)^2 - k^2 exactly the hold times strictly within k of t/2 win
+        for time_limit in [1i128 << 54, 1 << 62, 1 << 63, (1 << 63) + 6] {
+            for k in [1i128, 2, 12345, 1 << 40] {
+                let half = time_limit / 2;
+                assert_eq!(winning_range(half * half - k * k, time_limit), Some(2 * k - 1));
+            }
+        }
+        assert_eq!(isqrt(u128::MAX), u64::MAX as u128);
+        assert_eq!(isqrt((1 << 100) - 1), (1 << 50) - 1);
+    }
+
+    fn random_physics(rng: &mut Rng) -> Physics {
+        let mut stages = Vec::new();
+        for _ in 0..rng.below(3) {
+            stages.push(Stage { duration: Some(rng.below(12) + 1), acceleration: rng.below(4) });
+        }
+        if stages.is_empty() || rng.below(2) == 0 {
+            stages.push(Stage { duration: None, acceleration: rng.below(4) });
+        }
+        Physics {
+            stages,
+            max_speed: if rng.below(2) == 0 { Some(rng.below(30)) } else { None },
+            delay: if rng.below(2) == 0 { rng.below(10) } else { 0 },
+        }
+    }
+
+    #[test]
+    fn test_physics_matches_brute_force() {
+        let mut rng = Rng(0xb0a7);
+        for _ in 0..3000 {
+            let physics = random_physics(&mut rng);
+            physics.validate().unwrap();
+            let time_limit = rng.below(80);
+            let best = (0..=time_limit).map(|hold| physics.distance(hold, time_limit).unwrap()).max().unwrap();
+            let current_best_distance = rng.below(best + 6) - 3;
+            assert_eq!(
+                physics.count_wins(current_best_distance, time_limit),
+                physics.count_wins_brute_force(current_best_distance, time_limit),
+                "{:?} time {} distance {}", physics, time_limit, current_best_distance,
+            );
+        }
+    }
+
+    #[test]
+    fn test_physics_models() {
+        // the standard boat with the general search instead of the closed form
+        let mut standard = Physics::standard();
+        standard.stages.push(Stage { duration: None, acceleration: 0 });
+        standard.stages[0].duration = Some(1 << 40);
+        assert_eq!(standard.count_wins(940200, 71530), Some(71503));
+        assert_eq!(standard.count_wins(9, 7), Some(4));
+
+        // twice the acceleration: 2 * h * (7 - h) > 9 for h in 1..=6
+        assert_eq!(Physics::accelerating(2).count_wins(9, 7), Some(6));
+        assert_eq!(Physics::accelerating(0).count_wins(0, 7), Some(0));
+        assert_eq!(Physics::accelerating(0).count_wins(-1, 7), Some(8));
+
+        // capped at 2: the distances are 0 1*6 2*5 2*4 2*3 2*2 2*1 0
+        let capped = Physics { max_speed: Some(2), ..Physics::standard() };
+        assert_eq!(capped.distance(5, 7), Some(4));
+        assert_eq!(capped.count_wins(5, 7), Some(4));
+
+        // delayed by 2: h * (5 - h) for the holds up to 5
+        let delayed = Physics { delay: 2, ..Physics::standard() };
+        assert_eq!(delayed.count_wins(3, 7), Some(4));
+        assert_eq!(delayed.count_wins(-1, 7), Some(8));
+        assert_eq!(delayed.count_wins(0, 1), Some(0));
+
+        // one slow millisecond, then fast charging: speeds 0 1 4 7 10 ...
+        let staged = Physics { stages: vec![Stage { duration: Some(1), acceleration: 1 }, Stage { duration: None, acceleration: 3 }], ..Physics::standard() };
+        assert_eq!(staged.speed(4), Some(10));
+        assert_eq!(staged.count_wins(20, 7), Some(3));
+
+        // large races stay exact with the general search
+        let large = Physics { max_speed: Some(1 << 40), ..Physics::accelerating(3) };
+        assert_eq!(large.count_wins(0, 1 << 60), Some((1 << 60) - 1));
+        // the longest distance is reached as soon as the speed is capped
+        let peak = large.distance(((1 << 40) + 2) / 3, 1 << 60).unwrap();
+        assert_eq!(large.count_wins(peak, 1 << 60), Some(0));
+        assert_eq!(large.count_wins(peak - 1, 1 << 60), Some(1));
+        assert_eq!(Physics::standard().count_wins(0, i128::MAX), None);
+    }
+
+    #[test]
+    fn test_read_physics() {
+        let args = |line: &str| line.split(' ').map(String::from).collect::<Vec<_>>();
+        assert_eq!(read_physics(&args("wait_for_it")).unwrap(), Physics::standard());
+        assert_eq!(read_physics(&args("wait_for_it --acceleration 3")).unwrap(), Physics::accelerating(3));
+        let physics = read_physics(&args("wait_for_it --stages 5:1,10:2,3 --max-speed 20 --delay 4")).unwrap();
+        assert_eq!(
+            physics.stages,
+            vec![
+                Stage { duration: Some(5), acceleration: 1 },
+                Stage { duration: Some(10), acceleration: 2 },
+                Stage { duration: None, acceleration: 3 },
+            ]
+        );
+        assert_eq!((physics.max_speed, physics.delay), (Some(20), 4));
+        assert!(read_physics(&args("wait_for_it --stages 3,5:1")).is_err());
+        assert!(read_physics(&args("wait_for_it --stages 0:1")).is_err());
+        assert!(read_physics(&args("wait_for_it --acceleration -1")).is_err());
+        assert!(read_physics(&args("wait_for_it --acceleration 1 --stages 1")).is_err());
+        assert!(read_physics(&args("wait_for_it --delay -2")).is_err());
+        assert!(read_physics(&args("wait_for_it --max-speed x")).is_err());
+    }
+
+    // random_sheet writes a race sheet with up to five races and returns it with the races, columns are padded like the
+    // puzzle input
+    fn random_sheet(rng: &mut Rng) -> (String, Vec<(i128, i128)>) {
+        let races: Vec<(i128, i128)> = (0..1 + rng.below(5)).map(|_| (rng.below(100), rng.below(2000))).collect();
+        let mut times = String::from("Time:    ");
+        let mut distances = String::from("Distance:");
+        for (time, distance) in &races {
+            let width = 2 + rng.below(3) as usize;
+            times += &format!(" {:>width$}", time, width = width + 2);
+            distances += &format!(" {:>width$}", distance, width = width + 2);
+        }
+        (format!("{}\n{}\n", times, distances), races)
     }
-    wins
-}
\ No newline at end of file
+
+    #[test]
+    fn test_random_sheets() {
+        let mut rng = Rng(0x5ee7);
+        for _ in 0..500 {
+            let (sheet, races) = random_sheet(&mut rng);
+            assert_eq!(read_races(sheet.lines(), Kerning::Separate).unwrap(), races);
+            let joined = |numbers: Vec<i128>| numbers.iter().map(|n| n.to_string()).collect::<String>().parse::<i128>().unwrap();
+            let time = joined(races.iter().map(|race| race.0).collect());
+            let distance = joined(races.iter().map(|race| race.1).collect());
+            assert_eq!(read_races(sheet.lines(), Kerning::Joined).unwrap(), [(time, distance)]);
+            // a sheet with a single race reads the same either way
+            if races.len() == 1 {
+                assert_eq!(read_races(sheet.lines(), Kerning::Joined).unwrap(), races);
+            }
+            let product = races.iter().map(|&(time, distance)| brute_force(distance, time)).product::<i128>();
+            assert_eq!(calculate_number_of_wins_multiplied(&races, &Physics::standard()), Some(product));
+        }
+    }
+
+    #[test]
+    fn test_win_count_invariants() {
+        let mut rng = Rng(0x1a7);
+        for _ in 0..2000 {
+            let time_limit = rng.below(300);
+            let current_best_distance = rng.below(time_limit * time_limit / 4 + 10);
+            let wins = winning_range(current_best_distance, time_limit).unwrap();
+            // a better record never gives more ways to win, more time never fewer
+            assert!(winning_range(current_best_distance + 1 + rng.below(50), time_limit).unwrap() <= wins);
+            assert!(winning_range(current_best_distance, time_limit + 1 + rng.below(50)).unwrap() >= wins);
+            // holding h and time_limit - h go the same distance, so the count is odd exactly for even times with wins
+            assert_eq!(wins % 2 == 1, wins > 0 && time_limit % 2 == 0);
+
+            // slowing the boat down never gives more ways to win
+            let physics = random_physics(&mut rng);
+            let physics_wins = physics.count_wins(current_best_distance, time_limit).unwrap();
+            let delayed = Physics { delay: physics.delay + 1 + rng.below(5), ..physics.clone() };
+            assert!(delayed.count_wins(current_best_distance, time_limit).unwrap() <= physics_wins);
+            let capped = Physics { max_speed: Some(physics.max_speed.map_or(rng.below(40), |max_speed| max_speed / 2)), ..physics.clone() };
+            assert!(capped.count_wins(current_best_distance, time_limit).unwrap() <= physics_wins);
+        }
+    }
+}
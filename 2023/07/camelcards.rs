@@ -1,81 +1,363 @@
-use core::panic;
+use std::cmp::Reverse;
+use std::collections::BinaryHeap;
 use std::io::{self, BufRead};
 
-#[derive(PartialEq, Eq, PartialOrd, Ord, Copy, Clone)]
-enum Card {
-    Ace = 13,
-    King = 12,
-    Queen = 11,
-    Ten = 10,
-    Nine = 9,
-    Eight = 8,
-    Seven = 7,
-    Six = 6,
-    Five = 5,
-    Four = 4,
-    Three = 3,
-    Two = 2,
-    Joker = 1,
-}
-
-#[derive(PartialEq, Eq, PartialOrd, Ord)]
-enum HandType {
-    HighCard,
-    OnePair,
-    TwoPairs,
-    ThreeOfAKind,
-    FullHouse,
-    FourOfAKind,
-    FiveOfAKind,
-}
-
-#[derive(Eq)]
+// Card is a card symbol, its strength is given by the deck it is dealt from.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone, Debug)]
+struct Card(char);
+
+impl std::fmt::Display for Card {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+// Cards parse from a single symbol of the standard deck.
+impl std::str::FromStr for Card {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let error = |column, kind| ParseError { line: 1, column, kind };
+        let mut symbols = s.chars();
+        match (symbols.next(), symbols.next()) {
+            (None, _) => Err(error(1, ParseErrorKind::MissingCard)),
+            (Some(symbol), None) => Deck::standard().card(symbol).ok_or(error(1, ParseErrorKind::InvalidCard(symbol))),
+            (Some(_), Some(extra)) => Err(error(2, ParseErrorKind::UnexpectedCharacter(extra))),
+        }
+    }
+}
+
+// MAX_HAND_SIZE bounds the hand size of a deck file, so a typo in the file can't make a single hand exhaust memory.
+const MAX_HAND_SIZE: usize = 1000;
+
+// Deck describes which cards exist, how strong they are and how many of them make up a hand.
+#[derive(Clone, Debug, PartialEq)]
+struct Deck {
+    // card symbols from weakest to strongest
+    ranking: Vec<char>,
+    hand_size: usize,
+    // the card that is wild under Ruleset::JokersWild
+    joker: Option<char>,
+}
+
+impl Deck {
+    // standard is the camel cards deck of the puzzle with J as the joker
+    fn standard() -> Deck {
+        Deck {
+            ranking: "23456789TJQKA".chars().collect(),
+            hand_size: 5,
+            joker: Some('J'),
+        }
+    }
+
+    fn card(&self, symbol: char) -> Option<Card> {
+        if self.ranking.contains(&symbol) {
+            Some(Card(symbol))
+        } else {
+            None
+        }
+    }
+
+    // strength orders cards when breaking ties between hands of the same type
+    fn strength(&self, card: Card, ruleset: Ruleset) -> usize {
+        if self.is_joker(card, ruleset) {
+            return 0;
+        }
+        self.ranking.iter().position(|symbol| *symbol == card.0).map_or(0, |position| position + 1)
+    }
+
+    fn is_joker(&self, card: Card, ruleset: Ruleset) -> bool {
+        ruleset == Ruleset::JokersWild && self.joker == Some(card.0)
+    }
+
+    // read_config reads a deck from lines of "key = value", # starts a comment:
+    //
+    //   cards = 23456789TJQKA
+    //   hand size = 5
+    //   joker = J
+    //
+    // cards lists the symbols from weakest to strongest and is required. The hand size defaults to 5 and is at most
+    // MAX_HAND_SIZE, without a joker line both rulesets are the same.
+    fn read_config(config: &str) -> Result<Deck, Box<dyn std::error::Error>> {
+        let mut ranking = None;
+        let mut hand_size = 5;
+        let mut joker = None;
+        for (i, line) in config.lines().enumerate() {
+            let line = line.split('#').next().unwrap_or_default().trim();
+            if line.is_empty() {
+                continue;
+            }
+            let (key, value) = line.split_once('=').ok_or_else(|| format!("deck line {}: expected key = value", i + 1))?;
+            let value = value.trim();
+            match key.trim() {
+                "cards" => {
+                    let cards: Vec<char> = value.chars().collect();
+                    for (j, card) in cards.iter().enumerate() {
+                        if card.is_whitespace() {
+                            return Err(format!("deck line {}: card symbols cannot be whitespace", i + 1).into());
+                        }
+                        if cards[..j].contains(card) {
+                            return Err(format!("deck line {}: card {:?} is listed twice", i + 1, card).into());
+                        }
+                    }
+                    ranking = Some(cards);
+                }
+                "hand size" => {
+                    hand_size = value.parse().map_err(|e| format!("deck line {}: invalid hand size: {}", i + 1, e))?;
+                    if hand_size == 0 || hand_size > MAX_HAND_SIZE {
+                        return Err(format!("deck line {}: hand size must be between 1 and {}", i + 1, MAX_HAND_SIZE).into());
+                    }
+                }
+                "joker" => {
+                    let mut symbols = value.chars();
+                    match (symbols.next(), symbols.next()) {
+                        (Some(symbol), None) => joker = Some(symbol),
+                        _ => return Err(format!("deck line {}: the joker must be a single card", i + 1).into()),
+                    }
+                }
+                key => return Err(format!("deck line {}: unknown key {:?}", i + 1, key).into()),
+            }
+        }
+        let ranking = ranking.ok_or("deck has no cards line")?;
+        if ranking.is_empty() {
+            return Err("deck has no cards".into());
+        }
+        if let Some(joker) = joker {
+            if !ranking.contains(&joker) {
+                return Err(format!("joker {:?} is not one of the cards", joker).into());
+            }
+        }
+        Ok(Deck { ranking, hand_size, joker })
+    }
+}
+
+// Ruleset decides what the joker card of a deck is worth.
+#[derive(PartialEq, Eq, Copy, Clone, Debug)]
+enum Ruleset {
+    // the joker card is an ordinary card, for the standard deck a Jack between Queen and Ten (part 1)
+    Standard,
+    // the joker card is the weakest card for tie breaks, but it counts as whatever card makes the strongest hand type (part 2)
+    JokersWild,
+}
+
+impl std::fmt::Display for Ruleset {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Ruleset::Standard => write!(f, "standard"),
+            Ruleset::JokersWild => write!(f, "jokers wild"),
+        }
+    }
+}
+
+// HandType holds the sizes of the groups of equal cards in a hand, largest first.
+// Comparing them lexicographically gives the usual order for any hand size: five of a kind [5] beats
+// four of a kind [4, 1] beats a full house [3, 2] beats three of a kind [3, 1, 1] and so on.
+#[derive(PartialEq, Eq, PartialOrd, Ord, Clone, Debug)]
+struct HandType {
+    groups: Vec<usize>,
+}
+
+const NUMBER_NAMES: [&str; 11] = ["zero", "one", "two", "three", "four", "five", "six", "seven", "eight", "nine", "ten"];
+
+fn number_name(n: usize) -> String {
+    NUMBER_NAMES.get(n).map_or_else(|| n.to_string(), |name| name.to_string())
+}
+
+impl std::fmt::Display for HandType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let pairs = self.groups.iter().filter(|size| **size == 2).count();
+        let rest_single = |skip: usize| self.groups.iter().skip(skip).all(|size| *size == 1);
+        match self.groups[..] {
+            [] => write!(f, "no cards"),
+            [1, ..] => write!(f, "high card"),
+            [2, ..] if pairs == 1 => write!(f, "one pair"),
+            [2, ..] if rest_single(pairs) => write!(f, "{} pairs", number_name(pairs)),
+            [3, 2, ..] if rest_single(2) => write!(f, "full house"),
+            [largest, ..] if rest_single(1) => write!(f, "{} of a kind", number_name(largest)),
+            _ => {
+                let groups: Vec<String> = self.groups.iter().filter(|size| **size > 1).map(|size| size.to_string()).collect();
+                write!(f, "groups of {}", groups.join(" and "))
+            }
+        }
+    }
+}
+
+impl HandType {
+    // parse reads a hand type name as written by Display. The names leave out the single cards, so the hand size is needed
+    // to restore them: "four of a kind" is [4, 1] for five cards and [4, 1, 1] for six.
+    fn parse(name: &str, hand_size: usize) -> Option<HandType> {
+        let number = |word: &str| NUMBER_NAMES.iter().position(|n| *n == word).or_else(|| word.parse().ok()).filter(|n| *n <= hand_size);
+        let mut groups = if name == "no cards" || name == "high card" {
+            vec![]
+        } else if name == "full house" {
+            vec![3, 2]
+        } else if let Some(pairs) = name.strip_suffix(" pairs").or_else(|| name.strip_suffix(" pair")) {
+            vec![2; number(pairs)?]
+        } else if let Some(size) = name.strip_suffix(" of a kind") {
+            vec![number(size)?]
+        } else if let Some(sizes) = name.strip_prefix("groups of ") {
+            sizes.split(" and ").map(number).collect::<Option<Vec<usize>>>()?
+        } else {
+            return None;
+        };
+        let used = groups.iter().try_fold(0usize, |sum, size| sum.checked_add(*size))?;
+        if used > hand_size {
+            return None;
+        }
+        groups.resize(groups.len() + hand_size - used, 1);
+        groups.sort();
+        groups.reverse();
+        let typ = HandType { groups };
+        // only accept the name Display gives the type, e.g. not "groups of 2 and 2" for "two pairs"
+        if typ.to_string() == name {
+            Some(typ)
+        } else {
+            None
+        }
+    }
+}
+
+// Hand types parse from their names, assuming the five cards of the standard deck.
+impl std::str::FromStr for HandType {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        HandType::parse(s, Deck::standard().hand_size).ok_or_else(|| ParseError { line: 1, column: 1, kind: ParseErrorKind::UnknownHandType(s.to_string()) })
+    }
+}
+
+#[derive(Eq, Debug)]
 struct Hand {
-    cards: [Card; 5],
+    cards: Vec<Card>,
     typ: HandType,
     bid: u64,
+    // strengths of the cards in hand order, used to break ties
+    strengths: Vec<usize>,
 }
 
 impl Hand {
-    fn new(cards: [Card; 5], bid: u64) -> Self {
-        let typ = Hand::calculate_hand_type(&cards);
-        Hand { cards, typ, bid }
+    // parse reads a hand written as its cards, e.g. "KTJJT", the bid belongs to the input line and is left at 0
+    fn parse(s: &str, deck: &Deck, ruleset: Ruleset) -> Result<Hand, ParseError> {
+        Ok(Hand::new(parse_cards(s, 1, deck)?, 0, deck, ruleset))
+    }
+
+    fn new(cards: Vec<Card>, bid: u64, deck: &Deck, ruleset: Ruleset) -> Self {
+        let typ = Hand::calculate_hand_type(&cards, deck, ruleset);
+        let strengths = cards.iter().map(|card| deck.strength(*card, ruleset)).collect();
+        Hand { cards, typ, bid, strengths }
     }
 
-    fn calculate_hand_type(cards: &[Card]) -> HandType {
+    fn calculate_hand_type(cards: &[Card], deck: &Deck, ruleset: Ruleset) -> HandType {
         let cards: &mut [Card] = &mut cards.to_vec();
         cards.sort();
 
-        let mut counts = [0; 15];
+        let mut groups: Vec<usize> = Vec::new();
         let mut joker_count = 0;
-        for card in cards {
-            if *card == Card::Joker {
+        let mut previous = None;
+        for card in cards.iter() {
+            if deck.is_joker(*card, ruleset) {
                 joker_count += 1;
                 continue;
             }
-            counts[*card as usize] += 1;
-        }
-        let mut counts = counts.to_vec();
-        counts.sort();
-        counts.reverse();
-        let mut counts = counts.iter();
-        let highest_count = counts.next().unwrap() + joker_count;
-        let second_highest_count = counts.next().unwrap();
-        if highest_count == 5 {
-            HandType::FiveOfAKind
-        } else if highest_count == 4 {
-            HandType::FourOfAKind
-        } else if highest_count == 3 && *second_highest_count == 2 {
-            HandType::FullHouse
-        } else if highest_count == 3 {
-            HandType::ThreeOfAKind
-        } else if highest_count == 2 && *second_highest_count == 2 {
-            HandType::TwoPairs
-        } else if highest_count == 2 {
-            HandType::OnePair
-        } else {
-            HandType::HighCard
+            match groups.last_mut() {
+                Some(size) if previous == Some(*card) => *size += 1,
+                _ => groups.push(1),
+            }
+            previous = Some(*card);
+        }
+        groups.sort();
+        groups.reverse();
+        // jokers always join the largest group, that makes the lexicographically largest hand type
+        match groups.first_mut() {
+            Some(largest) => *largest += joker_count,
+            None if joker_count > 0 => groups.push(joker_count),
+            None => {}
+        }
+        HandType { groups }
+    }
+
+    // best_substitution replaces every joker with the card it stands for: the card of the largest group, the stronger
+    // one if groups tie. A hand of only jokers becomes the strongest card of the deck that is not a joker.
+    // Under Ruleset::Standard there are no jokers and the cards are returned unchanged.
+    fn best_substitution(cards: &[Card], deck: &Deck, ruleset: Ruleset) -> Vec<Card> {
+        let mut best: Option<(usize, usize, Card)> = None;
+        for card in cards.iter().filter(|card| !deck.is_joker(**card, ruleset)) {
+            let key = (cards.iter().filter(|other| *other == card).count(), deck.strength(*card, ruleset), *card);
+            if best < Some(key) {
+                best = Some(key);
+            }
+        }
+        let strongest = || {
+            deck.ranking.iter()
+                .map(|symbol| Card(*symbol))
+                .filter(|card| !deck.is_joker(*card, ruleset))
+                .max_by_key(|card| deck.strength(*card, ruleset))
+        };
+        let target = match best.map(|(_, _, card)| card).or_else(strongest) {
+            Some(card) => card,
+            // a deck of only jokers has nothing to substitute
+            None => return cards.to_vec(),
+        };
+        cards.iter().map(|card| if deck.is_joker(*card, ruleset) { target } else { *card }).collect()
+    }
+}
+
+// Explanation shows how a hand is scored: the cards its jokers stand for, its type, rank and winnings.
+#[derive(Debug, PartialEq)]
+struct Explanation {
+    rank: usize,
+    cards: Vec<Card>,
+    substituted: Vec<Card>,
+    typ: HandType,
+    bid: u64,
+    winnings: u64,
+}
+
+impl std::fmt::Display for Explanation {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let cards: String = self.cards.iter().map(|card| card.0).collect();
+        let substituted: String = self.substituted.iter().map(|card| card.0).collect();
+        write!(f, "rank {}: {}", self.rank, cards)?;
+        if cards != substituted {
+            write!(f, " as {}", substituted)?;
+        }
+        write!(f, " ({}), bid {}, winnings {}", self.typ, self.bid, self.winnings)
+    }
+}
+
+// explain expects the hands sorted from weakest to strongest, like total_winnings
+fn explain(hands: &[Hand], deck: &Deck, ruleset: Ruleset) -> Vec<Explanation> {
+    let mut explanations = Vec::new();
+    for (rank, hand) in (1..).zip(hands.iter()) {
+        explanations.push(Explanation {
+            rank,
+            cards: hand.cards.clone(),
+            substituted: Hand::best_substitution(&hand.cards, deck, ruleset),
+            typ: hand.typ.clone(),
+            bid: hand.bid,
+            winnings: hand.bid * rank as u64,
+        });
+    }
+    explanations
+}
+
+// Hands are written as their cards only, the bid is part of the input line and not of the hand.
+impl std::fmt::Display for Hand {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        for card in &self.cards {
+            write!(f, "{}", card)?;
         }
+        Ok(())
+    }
+}
+
+// FromStr has no way to take a deck or ruleset, so it always uses the standard deck under the
+// standard rules. Hand::parse is the ruleset-aware form.
+impl std::str::FromStr for Hand {
+    type Err = ParseError;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Hand::parse(s, &Deck::standard(), Ruleset::Standard)
     }
 }
 
@@ -86,13 +368,7 @@ impl Ord for Hand {
             return l1;
         }
         // find higher card in case of tie
-        for i in 0..5 {
-            let l2 = self.cards[i].cmp(&other.cards[i]);
-            if l2 != std::cmp::Ordering::Equal {
-                return l2;
-            }
-        }
-        return std::cmp::Ordering::Equal;
+        self.strengths.cmp(&other.strengths)
     }
 }
 
@@ -108,59 +384,1079 @@ impl PartialEq for Hand {
     }
 }
 
-fn total_winnings_part_1(hands: &[Hand]) -> u64 {
-    let mut total = 0;
-    let mut multiplier = 1;
-    for hand in hands {
-        total += hand.bid * multiplier;
-        multiplier += 1;
+// total_winnings expects the hands sorted from weakest to strongest, it returns None if the total overflows.
+// The winnings of a single hand are at most the total, so explain and standings can't overflow once the total fits.
+fn total_winnings(hands: &[Hand]) -> Option<u64> {
+    let mut total: u64 = 0;
+    for (multiplier, hand) in (1..).zip(hands.iter()) {
+        total = total.checked_add(hand.bid.checked_mul(multiplier)?)?;
+    }
+    Some(total)
+}
+
+// Standing is a row of the standings table.
+#[derive(Debug, PartialEq)]
+struct Standing {
+    ruleset: Ruleset,
+    rank: usize,
+    cards: Vec<Card>,
+    typ: HandType,
+    bid: u64,
+    winnings: u64,
+    // first and last rank of the hands that are exactly as strong as this one
+    tie: Option<(usize, usize)>,
+}
+
+#[derive(Debug, PartialEq, Clone, Copy)]
+enum StandingsFormat {
+    Text,
+    Csv,
+    Json,
+}
+
+// standings expects the hands sorted from weakest to strongest, like total_winnings.
+//
+// Tie-breaking policy: hands with the same cards are exactly as strong and sorting keeps them in input order (the sort is
+// stable), so the tied hand that comes first in the input gets the lower rank. Such hands are marked as tied with the
+// ranks they share.
+fn standings(hands: &[Hand], ruleset: Ruleset) -> Vec<Standing> {
+    let mut standings: Vec<Standing> = Vec::new();
+    for (rank, hand) in (1..).zip(hands.iter()) {
+        let tie_start = match standings.last() {
+            Some(previous) if hands[previous.rank - 1].cmp(hand) == std::cmp::Ordering::Equal => previous.tie.map_or(previous.rank, |(start, _)| start),
+            _ => rank,
+        };
+        if tie_start != rank {
+            for standing in &mut standings[tie_start - 1..] {
+                standing.tie = Some((tie_start, rank));
+            }
+        }
+        standings.push(Standing {
+            ruleset,
+            rank,
+            cards: hand.cards.clone(),
+            typ: hand.typ.clone(),
+            bid: hand.bid,
+            winnings: hand.bid * rank as u64,
+            tie: if tie_start != rank { Some((tie_start, rank)) } else { None },
+        });
+    }
+    standings
+}
+
+fn json_string(s: &str) -> String {
+    let mut json = String::from("\"");
+    for c in s.chars() {
+        match c {
+            '"' => json += "\\\"",
+            '\\' => json += "\\\\",
+            '\n' => json += "\\n",
+            c if c.is_control() => json += &format!("\\u{:04x}", c as u32),
+            c => json.push(c),
+        }
+    }
+    json.push('"');
+    json
+}
+
+fn csv_field(s: &str) -> String {
+    if s.contains([',', '"', '\n']) {
+        format!("\"{}\"", s.replace('"', "\"\""))
+    } else {
+        s.to_string()
+    }
+}
+
+// write_standings writes a single document, the standings of several rulesets are told apart by their ruleset column.
+fn write_standings(standings: &[Standing], format: StandingsFormat) -> String {
+    let rows: Vec<(String, String)> = standings.iter().map(|standing| (standing.cards.iter().map(|card| card.0).collect(), standing.typ.to_string())).collect();
+    let mut out = String::new();
+    match format {
+        StandingsFormat::Text => {
+            let ruleset_width = standings.iter().map(|standing| standing.ruleset.to_string().len()).max().unwrap_or(0).max("ruleset".len());
+            let hand_width = rows.iter().map(|(cards, _)| cards.chars().count()).max().unwrap_or(0).max("hand".len());
+            let type_width = rows.iter().map(|(_, typ)| typ.len()).max().unwrap_or(0).max("type".len());
+            out += &format!("{:<ruleset_width$}  {:>5}  {:<hand_width$}  {:<type_width$}  {:>10}  {:>12}\n", "ruleset", "rank", "hand", "type", "bid", "winnings");
+            for (standing, (cards, typ)) in standings.iter().zip(rows.iter()) {
+                let tie = standing.tie.map_or(String::new(), |(first, last)| format!("  tied with ranks {}-{}", first, last));
+                out += &format!(
+                    "{:<ruleset_width$}  {:>5}  {:<hand_width$}  {:<type_width$}  {:>10}  {:>12}{}\n",
+                    standing.ruleset.to_string(), standing.rank, cards, typ, standing.bid, standing.winnings, tie,
+                );
+            }
+        }
+        StandingsFormat::Csv => {
+            out += "ruleset,rank,hand,type,bid,winnings,tie\n";
+            for (standing, (cards, typ)) in standings.iter().zip(rows.iter()) {
+                let tie = standing.tie.map_or(String::new(), |(first, last)| format!("{}-{}", first, last));
+                out += &format!(
+                    "{},{},{},{},{},{},{}\n",
+                    csv_field(&standing.ruleset.to_string()), standing.rank, csv_field(cards), csv_field(typ), standing.bid, standing.winnings, tie,
+                );
+            }
+        }
+        StandingsFormat::Json => {
+            out += "[";
+            for (i, (standing, (cards, typ))) in standings.iter().zip(rows.iter()).enumerate() {
+                out += if i == 0 { "\n" } else { ",\n" };
+                let tie = standing.tie.map_or("null".to_string(), |(first, last)| format!("[{}, {}]", first, last));
+                out += &format!(
+                    "  {{\"ruleset\": {}, \"rank\": {}, \"hand\": {}, \"type\": {}, \"bid\": {}, \"winnings\": {}, \"tie\": {}}}",
+                    json_string(&standing.ruleset.to_string()),
+                    standing.rank,
+                    json_string(cards),
+                    json_string(typ),
+                    standing.bid,
+                    standing.winnings,
+                    tie,
+                );
+            }
+            out += if standings.is_empty() { "]\n" } else { "\n]\n" };
+        }
+    }
+    out
+}
+
+// KeyLayout packs a hand into a single integer that orders exactly like Hand's Ord: first the group sizes of the hand type,
+// padded with zeros to the hand size, then the strengths of the cards in hand order.
+#[derive(Debug, PartialEq, Clone, Copy)]
+struct KeyLayout {
+    hand_size: usize,
+    group_bits: u32,
+    strength_bits: u32,
+}
+
+impl KeyLayout {
+    // new returns None if the keys of the deck do not fit into 128 bits
+    fn new(deck: &Deck) -> Option<KeyLayout> {
+        let bits = |max: usize| usize::BITS - max.leading_zeros();
+        let layout = KeyLayout { hand_size: deck.hand_size, group_bits: bits(deck.hand_size), strength_bits: bits(deck.ranking.len()) };
+        if layout.bits() <= u128::BITS as usize {
+            Some(layout)
+        } else {
+            None
+        }
+    }
+
+    fn bits(&self) -> usize {
+        self.hand_size.saturating_mul((self.group_bits + self.strength_bits) as usize)
+    }
+
+    fn key(&self, hand: &Hand) -> u128 {
+        let mut key: u128 = 0;
+        for i in 0..self.hand_size {
+            key = key << self.group_bits | hand.typ.groups.get(i).copied().unwrap_or(0) as u128;
+        }
+        for strength in &hand.strengths {
+            key = key << self.strength_bits | *strength as u128;
+        }
+        key
+    }
+}
+
+// radix_sort sorts (key, bid) pairs by key, looking at the lowest bits of the keys only.
+// It is a stable least significant digit sort, so equal keys keep their order like sort() does.
+fn radix_sort(entries: &mut Vec<(u128, u64)>, bits: usize) {
+    let mut buffer = vec![(0, 0); entries.len()];
+    for shift in (0..bits).step_by(8) {
+        let digit = |key: u128| ((key >> shift) & 0xff) as usize;
+        let mut offsets = [0usize; 257];
+        for (key, _) in entries.iter() {
+            offsets[digit(*key) + 1] += 1;
+        }
+        for i in 1..offsets.len() {
+            offsets[i] += offsets[i - 1];
+        }
+        for entry in entries.iter() {
+            let slot = &mut offsets[digit(entry.0)];
+            buffer[*slot] = *entry;
+            *slot += 1;
+        }
+        std::mem::swap(entries, &mut buffer);
+    }
+}
+
+// TopEntry is the key, input position, cards and bid of one of the strongest hands
+type TopEntry = (u128, usize, Vec<Card>, u64);
+
+// StreamRanking ranks hands as they are read and keeps only a packed key and the bid of every hand, which makes it
+// usable for hand lists that are too large to hold as Hand values.
+struct StreamRanking {
+    layout: KeyLayout,
+    // key and bid of every hand in input order
+    entries: Vec<(u128, u64)>,
+    // the top_k strongest hands so far with the weakest of them on top of the heap, hands that come later in the
+    // input win ties like they do in the sorted order
+    top: BinaryHeap<Reverse<TopEntry>>,
+    top_k: usize,
+}
+
+impl StreamRanking {
+    fn new(layout: KeyLayout, top_k: usize) -> StreamRanking {
+        StreamRanking { layout, entries: Vec::new(), top: BinaryHeap::new(), top_k }
+    }
+
+    // push adds the next hand of the input and returns its key
+    fn push(&mut self, hand: &Hand) -> u128 {
+        let key = self.layout.key(hand);
+        let index = self.entries.len();
+        self.entries.push((key, hand.bid));
+        if self.top_k > 0 {
+            self.top.push(Reverse((key, index, hand.cards.clone(), hand.bid)));
+            if self.top.len() > self.top_k {
+                self.top.pop();
+            }
+        }
+        key
+    }
+
+    fn total_winnings(&self) -> Option<u64> {
+        let mut sorted = self.entries.clone();
+        radix_sort(&mut sorted, self.layout.bits());
+        let mut total: u64 = 0;
+        for (multiplier, (_, bid)) in (1..).zip(sorted.iter()) {
+            total = total.checked_add(bid.checked_mul(multiplier)?)?;
+        }
+        Some(total)
+    }
+
+    // top returns rank, cards and bid of the strongest hands, strongest first
+    fn top(&self) -> Vec<(usize, Vec<Card>, u64)> {
+        let mut top: Vec<_> = self.top.iter().map(|Reverse(entry)| entry.clone()).collect();
+        top.sort();
+        top.reverse();
+        (0..).zip(top).map(|(i, (_, _, cards, bid))| (self.entries.len() - i, cards, bid)).collect()
+    }
+
+    // rank_of returns the rank of the hand at index in the input without sorting: one more than the number of weaker
+    // hands and of equal hands before it
+    fn rank_of(&self, index: usize) -> usize {
+        let (key, _) = self.entries[index];
+        let weaker = self.entries.iter().filter(|(other, _)| *other < key).count();
+        let earlier_ties = self.entries[..index].iter().filter(|(other, _)| *other == key).count();
+        weaker + earlier_ties + 1
     }
-    total
+}
+
+// stream_rankings reads the input once and ranks it under both rulesets with the packed keys.
+// --top prints the strongest hands, --rank the rank of the first hand holding the given cards.
+fn stream_rankings(lines: impl Iterator<Item = Result<String, io::Error>>, deck: &Deck, args: &[String]) -> Result<(), Box<dyn std::error::Error>> {
+    let layout = KeyLayout::new(deck).ok_or("the hands of this deck do not fit into a packed key")?;
+    let top_k: usize = flag_value(args, "--top").map_or(Ok(0), str::parse)?;
+    let query: Option<Vec<Card>> = flag_value(args, "--rank").map(|cards| cards.chars().map(Card).collect());
+    let rulesets = [Ruleset::Standard, Ruleset::JokersWild];
+    let mut rankings = [StreamRanking::new(layout, top_k), StreamRanking::new(layout, top_k)];
+    let mut query_index = None;
+    for (i, line) in lines.enumerate() {
+        let (cards, bid) = parse_line(&line?, i + 1, deck)?;
+        if query_index.is_none() && query.as_ref() == Some(&cards) {
+            query_index = Some(i);
+        }
+        for (ranking, ruleset) in rankings.iter_mut().zip(rulesets) {
+            ranking.push(&Hand::new(cards.clone(), bid, deck, ruleset));
+        }
+    }
+
+    for (ranking, ruleset) in rankings.iter().zip(rulesets) {
+        for (rank, cards, bid) in ranking.top() {
+            let cards: String = cards.iter().map(|card| card.0).collect();
+            println!("rank {}: {}, bid {}", rank, cards, bid);
+        }
+        if let Some(query) = &query {
+            let query: String = query.iter().map(|card| card.0).collect();
+            match query_index {
+                Some(index) => println!("{} ranks {} of {} ({})", query, ranking.rank_of(index), ranking.entries.len(), ruleset),
+                None => println!("{} is not in the input", query),
+            }
+        }
+        let wins = ranking.total_winnings().ok_or(format!("total winnings ({}) overflow", ruleset))?;
+        println!("total winnings ({}): {}", ruleset, wins);
+    }
+    Ok(())
+}
+
+// Rng is a xorshift generator, seeded so that sampled odds are reproducible.
+struct Rng(u64);
+
+impl Rng {
+    fn new(seed: u64) -> Rng {
+        // xorshift never leaves 0
+        Rng(if seed == 0 { 0x9e3779b97f4a7c15 } else { seed })
+    }
+
+    fn next(&mut self, n: usize) -> usize {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 % n as u64) as usize
+    }
+}
+
+// decks with at most this many different hands are enumerated instead of sampled
+const ENUMERATION_LIMIT: u128 = 1 << 19;
+
+// Distribution holds the hands a random opponent can be dealt.
+#[derive(Debug)]
+struct Distribution {
+    // every hand of the deck, or a sample of them for large decks, sorted from weakest to strongest
+    hands: Vec<Hand>,
+    exhaustive: bool,
+}
+
+impl Distribution {
+    // new enumerates all hands of the deck if there are at most ENUMERATION_LIMIT of them, otherwise it draws samples
+    // random hands. Every card of a hand is drawn independently, a deck never runs out of a card.
+    fn new(deck: &Deck, ruleset: Ruleset, samples: usize, rng: &mut Rng) -> Distribution {
+        let symbols = deck.ranking.len();
+        let total = u32::try_from(deck.hand_size).ok().and_then(|hand_size| (symbols as u128).checked_pow(hand_size));
+        let exhaustive = total.is_some_and(|total| total <= ENUMERATION_LIMIT);
+        let mut hands = Vec::new();
+        if let (true, Some(total)) = (exhaustive, total) {
+            for index in 0..total as usize {
+                let mut rest = index;
+                let mut cards = Vec::new();
+                for _ in 0..deck.hand_size {
+                    cards.push(Card(deck.ranking[rest % symbols]));
+                    rest /= symbols;
+                }
+                hands.push(Hand::new(cards, 0, deck, ruleset));
+            }
+        } else {
+            for _ in 0..samples {
+                let cards = (0..deck.hand_size).map(|_| Card(deck.ranking[rng.next(symbols)])).collect();
+                hands.push(Hand::new(cards, 0, deck, ruleset));
+            }
+        }
+        hands.sort();
+        Distribution { hands, exhaustive }
+    }
+
+    // type_probabilities returns the share of every hand type, from weakest to strongest
+    fn type_probabilities(&self) -> Vec<(HandType, f64)> {
+        let mut probabilities: Vec<(HandType, f64)> = Vec::new();
+        let share = 1.0 / self.hands.len() as f64;
+        // sorted hands are grouped by type already
+        for hand in &self.hands {
+            match probabilities.last_mut() {
+                Some((typ, probability)) if *typ == hand.typ => *probability += share,
+                _ => probabilities.push((hand.typ.clone(), share)),
+            }
+        }
+        probabilities
+    }
+
+    // odds returns the chances that a random hand is weaker than or exactly as strong as hand
+    fn odds(&self, hand: &Hand) -> (f64, f64) {
+        let weaker = self.hands.partition_point(|other| other < hand);
+        let not_stronger = self.hands.partition_point(|other| other <= hand);
+        let total = self.hands.len() as f64;
+        (weaker as f64 / total, (not_stronger - weaker) as f64 / total)
+    }
+
+    // win_probability is the chance that hand beats every one of opponents random hands, a tie is not a win
+    fn win_probability(&self, hand: &Hand, opponents: u32) -> f64 {
+        let (weaker, _) = self.odds(hand);
+        // weaker is at most 1, so clamping the exponent does not change the result
+        weaker.powi(i32::try_from(opponents).unwrap_or(i32::MAX))
+    }
+
+    // expected_winnings is the bid times the expected rank of hand among itself and opponents random hands.
+    // A tied opponent ends up below or above the hand with the same chance.
+    fn expected_winnings(&self, hand: &Hand, opponents: u32) -> f64 {
+        let (weaker, tied) = self.odds(hand);
+        hand.bid as f64 * (1.0 + opponents as f64 * (weaker + tied / 2.0))
+    }
+}
+
+fn flag_value<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+    args.iter().position(|arg| arg == flag).and_then(|i| args.get(i + 1)).map(String::as_str)
 }
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
+    let args: Vec<String> = std::env::args().collect();
+    let deck = match flag_value(&args, "--deck") {
+        Some(path) => Deck::read_config(&std::fs::read_to_string(path)?)?,
+        None => Deck::standard(),
+    };
+
+    let show_explanation = args.contains(&"--explain".to_string());
+    let standings_format = match flag_value(&args, "--standings") {
+        None => None,
+        Some("text") => Some(StandingsFormat::Text),
+        Some("csv") => Some(StandingsFormat::Csv),
+        Some("json") => Some(StandingsFormat::Json),
+        Some(format) => return Err(format!("unknown standings format {:?}, expected text, csv or json", format).into()),
+    };
+    // --odds reports the hand type probabilities and the expected winnings of the bid table against --opponents random hands
+    let show_odds = args.contains(&"--odds".to_string());
+    let opponents: u32 = flag_value(&args, "--opponents").map_or(Ok(1), str::parse)?;
+    let samples: usize = flag_value(&args, "--samples").map_or(Ok(100_000), str::parse)?;
+    if samples == 0 {
+        // a deck too large to enumerate would have no hands to compute the odds from
+        return Err("--samples must be at least 1".into());
+    }
+    let seed: u64 = flag_value(&args, "--seed").map_or(Ok(1), str::parse)?;
+
     let stdin = io::stdin();
-    let lines = stdin.lock().lines();
+    if args.contains(&"--stream".to_string()) {
+        return stream_rankings(stdin.lock().lines(), &deck, &args);
+    }
+    let lines: Vec<String> = stdin.lock().lines().collect::<Result<_, _>>()?;
 
-    let mut input = read_input(lines)?;
-    input.sort();
-    let wins = total_winnings_part_1(&input);
-    println!("total winnings: {}", wins);
+    // with --standings csv or json stdout only holds the standings document, everything else goes to stderr
+    let machine_readable = matches!(standings_format, Some(StandingsFormat::Csv | StandingsFormat::Json));
+    let report = |line: String| if machine_readable { eprintln!("{}", line) } else { println!("{}", line) };
+    let mut table = Vec::new();
+    for ruleset in [Ruleset::Standard, Ruleset::JokersWild] {
+        let mut input = read_input(lines.iter().map(String::as_str), &deck, ruleset)?;
+        // stable, exact ties keep their input order
+        input.sort();
+        let wins = total_winnings(&input).ok_or(format!("total winnings ({}) overflow", ruleset))?;
+        if standings_format.is_some() {
+            let ruleset_table = standings(&input, ruleset);
+            for standing in &ruleset_table {
+                match standing.tie {
+                    Some((first, last)) if first == standing.rank => {
+                        let cards: String = standing.cards.iter().map(|card| card.0).collect();
+                        eprintln!("tie ({}): ranks {}-{} all hold {}, ranked in input order", ruleset, first, last, cards);
+                    }
+                    _ => {}
+                }
+            }
+            table.extend(ruleset_table);
+        }
+        if show_explanation {
+            for explanation in explain(&input, &deck, ruleset) {
+                report(explanation.to_string());
+            }
+        }
+        report(format!("total winnings ({}): {}", ruleset, wins));
+        if show_odds {
+            let distribution = Distribution::new(&deck, ruleset, samples, &mut Rng::new(seed));
+            let source = if distribution.exhaustive { "all" } else { "sampled" };
+            report(format!("hand types ({}, {} {} hands):", ruleset, source, distribution.hands.len()));
+            for (typ, probability) in distribution.type_probabilities() {
+                report(format!("  {}: {:.6}", typ, probability));
+            }
+            let expected: f64 = input.iter().map(|hand| distribution.expected_winnings(hand, opponents)).sum();
+            report(format!("expected winnings against {} random opponents per hand: {:.2}", opponents, expected));
+            if let Some(strongest) = input.last() {
+                let cards: String = strongest.cards.iter().map(|card| card.0).collect();
+                report(format!("strongest hand {} wins with probability {:.6}", cards, distribution.win_probability(strongest, opponents)));
+            }
+        }
+    }
+    if let Some(format) = standings_format {
+        print!("{}", write_standings(&table, format));
+    }
 
     Ok(())
 }
 
-fn read_input(
-    lines: impl Iterator<Item = Result<String, io::Error>>,
-) -> Result<Vec<Hand>, Box<dyn std::error::Error>> {
+#[derive(Debug, PartialEq)]
+enum ParseErrorKind {
+    InvalidCard(char),
+    HandSize { found: usize, expected: usize },
+    MissingBid,
+    InvalidBid(String),
+    MissingCard,
+    UnexpectedCharacter(char),
+    UnknownHandType(String),
+}
+
+#[derive(Debug, PartialEq)]
+struct ParseError {
+    // 1-based line and column of the offending character
+    line: usize,
+    column: usize,
+    kind: ParseErrorKind,
+}
+
+impl std::fmt::Display for ParseError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        write!(f, "line {}, column {}: ", self.line, self.column)?;
+        match &self.kind {
+            ParseErrorKind::InvalidCard(c) => write!(f, "invalid card {:?}", c),
+            ParseErrorKind::HandSize { found, expected } => write!(f, "hand has {} cards, but hands have {}", found, expected),
+            ParseErrorKind::MissingBid => write!(f, "expected a bid after the hand"),
+            ParseErrorKind::InvalidBid(bid) => write!(f, "invalid bid {:?}", bid),
+            ParseErrorKind::MissingCard => write!(f, "expected a card"),
+            ParseErrorKind::UnexpectedCharacter(c) => write!(f, "unexpected character {:?}", c),
+            ParseErrorKind::UnknownHandType(name) => write!(f, "unknown hand type {:?}", name),
+        }
+    }
+}
+
+impl std::error::Error for ParseError {}
+
+fn read_input<'a>(lines: impl Iterator<Item = &'a str>, deck: &Deck, ruleset: Ruleset) -> Result<Vec<Hand>, ParseError> {
     let mut hands = Vec::new();
-    for line in lines {
-        let line = line?;
-        let parts = line.split_once(" ").ok_or("invalid input")?;
-        let mut cards: [Card; 5] = [Card::Ace; 5];
-        for (i, card) in parts.0.chars().enumerate() {
-            let card = match card {
-                'A' => Card::Ace,
-                'K' => Card::King,
-                'Q' => Card::Queen,
-                'J' => Card::Joker,
-                'T' => Card::Ten,
-                '9' => Card::Nine,
-                '8' => Card::Eight,
-                '7' => Card::Seven,
-                '6' => Card::Six,
-                '5' => Card::Five,
-                '4' => Card::Four,
-                '3' => Card::Three,
-                '2' => Card::Two,
-                _ => panic!("invalid card"),
-            };
-            cards[i] = card;
-        }
-
-        let bid = parts.1.parse::<u64>()?;
-        let hand = Hand::new(cards.try_into().unwrap(), bid);
-        hands.push(hand);
+    for (i, line) in lines.enumerate() {
+        let (cards, bid) = parse_line(line, i + 1, deck)?;
+        hands.push(Hand::new(cards, bid, deck, ruleset));
     }
     Ok(hands)
 }
+
+// parse_line reads a line of "<cards> <bid>", line_number is only used for errors.
+fn parse_line(line: &str, line_number: usize, deck: &Deck) -> Result<(Vec<Card>, u64), ParseError> {
+    let error = |column, kind| ParseError { line: line_number, column, kind };
+    let hand_end = line.find(char::is_whitespace).unwrap_or(line.len());
+    let cards = parse_cards(&line[..hand_end], line_number, deck)?;
+
+    let mut chars = line[hand_end..].chars().zip(cards.len()..).skip_while(|(c, _)| c.is_whitespace()).peekable();
+    let Some(&(_, bid_column)) = chars.peek() else {
+        return Err(error(line.chars().count() + 1, ParseErrorKind::MissingBid));
+    };
+    let bid: String = chars.map(|(c, _)| c).collect();
+    let bid = bid.trim_end();
+    match bid.parse::<u64>() {
+        Ok(value) if bid.bytes().all(|b| b.is_ascii_digit()) => Ok((cards, value)),
+        _ => Err(error(bid_column + 1, ParseErrorKind::InvalidBid(bid.to_string()))),
+    }
+}
+
+// parse_cards reads a hand written as its card symbols, line_number is only used for errors.
+fn parse_cards(text: &str, line_number: usize, deck: &Deck) -> Result<Vec<Card>, ParseError> {
+    let error = |column, kind| ParseError { line: line_number, column, kind };
+    let mut cards = Vec::new();
+    for (column, symbol) in text.chars().enumerate() {
+        cards.push(deck.card(symbol).ok_or_else(|| error(column + 1, ParseErrorKind::InvalidCard(symbol)))?);
+    }
+    if cards.len() != deck.hand_size {
+        // point at the first card too many or at the end of a short hand
+        let column = std::cmp::min(cards.len(), deck.hand_size) + 1;
+        return Err(error(column, ParseErrorKind::HandSize { found: cards.len(), expected: deck.hand_size }));
+    }
+    Ok(cards)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const EXAMPLE: &str = "32T3K 765\nT55J5 684\nKK677 28\nKTJJT 220\nQQQJA 483";
+
+    fn cards(hand: &str) -> Vec<Card> {
+        hand.chars().map(Card).collect()
+    }
+
+    fn hand_type(hand: &str, deck: &Deck, ruleset: Ruleset) -> HandType {
+        Hand::calculate_hand_type(&cards(hand), deck, ruleset)
+    }
+
+    fn winnings(input: &str, ruleset: Ruleset) -> u64 {
+        let mut hands = read_input(input.lines(), &Deck::standard(), ruleset).unwrap();
+        hands.sort();
+        total_winnings(&hands).unwrap()
+    }
+
+    #[test]
+    fn test_example() {
+        assert_eq!(winnings(EXAMPLE, Ruleset::Standard), 6440);
+        assert_eq!(winnings(EXAMPLE, Ruleset::JokersWild), 5905);
+    }
+
+    #[test]
+    fn test_input() {
+        let input = include_str!("input.txt");
+        assert_eq!(winnings(input, Ruleset::Standard), 250347426);
+        assert_eq!(winnings(input, Ruleset::JokersWild), 251224870);
+    }
+
+    #[test]
+    fn test_rulesets() {
+        let deck = Deck::standard();
+        assert_eq!(hand_type("KTJJT", &deck, Ruleset::Standard).to_string(), "two pairs");
+        assert_eq!(hand_type("KTJJT", &deck, Ruleset::JokersWild).to_string(), "four of a kind");
+        assert_eq!(hand_type("JJJJJ", &deck, Ruleset::JokersWild).to_string(), "five of a kind");
+
+        // a Jack beats a Ten, a Joker loses against a Two
+        let hand = |cards_: &str, ruleset| Hand::new(cards(cards_), 0, &deck, ruleset);
+        assert!(hand("J2345", Ruleset::Standard) > hand("T2345", Ruleset::Standard));
+        assert!(hand("J2345", Ruleset::JokersWild) < hand("22345", Ruleset::JokersWild));
+    }
+
+    #[test]
+    fn test_hand_types() {
+        let deck = Deck::standard();
+        let names: Vec<String> = ["23456", "22345", "22335", "22234", "22233", "22223", "22222"]
+            .iter()
+            .map(|hand| hand_type(hand, &deck, Ruleset::Standard).to_string())
+            .collect();
+        assert_eq!(names, ["high card", "one pair", "two pairs", "three of a kind", "full house", "four of a kind", "five of a kind"]);
+        // listed from weakest to strongest
+        let types: Vec<HandType> = ["23456", "22345", "22335", "22234", "22233", "22223", "22222"]
+            .iter()
+            .map(|hand| hand_type(hand, &deck, Ruleset::Standard))
+            .collect();
+        assert!(types.windows(2).all(|pair| pair[0] < pair[1]));
+
+        let six = Deck { hand_size: 6, ..Deck::standard() };
+        let names: Vec<String> = ["234567", "223344", "222333", "222233", "222222", "2222JJ"]
+            .iter()
+            .map(|hand| hand_type(hand, &six, Ruleset::JokersWild).to_string())
+            .collect();
+        assert_eq!(names, ["high card", "three pairs", "groups of 3 and 3", "groups of 4 and 2", "six of a kind", "six of a kind"]);
+        assert!(hand_type("222333", &six, Ruleset::Standard) > hand_type("222334", &six, Ruleset::Standard));
+        assert!(hand_type("222333", &six, Ruleset::Standard) < hand_type("222234", &six, Ruleset::Standard));
+        assert!(hand_type("223344", &six, Ruleset::Standard) > hand_type("223345", &six, Ruleset::Standard));
+        assert!(hand_type("223344", &six, Ruleset::Standard) < hand_type("222345", &six, Ruleset::Standard));
+    }
+
+    #[test]
+    fn test_custom_deck() {
+        let deck = Deck::read_config("# a tiny deck\ncards = xyz\nhand size = 3\njoker = x\n").unwrap();
+        assert_eq!(deck, Deck { ranking: vec!['x', 'y', 'z'], hand_size: 3, joker: Some('x') });
+        let mut hands = read_input("zyx 1\nyyz 10\nxxy 100".lines(), &deck, Ruleset::JokersWild).unwrap();
+        hands.sort();
+        // xxy is three of a kind with its jokers, zyx pairs up with its joker and beats yyz on the first card
+        let order: Vec<u64> = hands.iter().map(|hand| hand.bid).collect();
+        assert_eq!(order, vec![10, 1, 100]);
+
+        assert!(Deck::read_config("hand size = 5").is_err());
+        assert!(Deck::read_config("cards = 2345\njoker = J").is_err());
+        assert!(Deck::read_config("cards = 2245").is_err());
+        let error = |config: &str| Deck::read_config(config).unwrap_err().to_string();
+        assert_eq!(error("cards = 2345\nhand size = 0"), "deck line 2: hand size must be between 1 and 1000");
+        assert_eq!(error("hand size = 99999999999\ncards = 2345"), "deck line 1: hand size must be between 1 and 1000");
+        assert_eq!(Deck::read_config("cards = 2345\nhand size = 1000").unwrap().hand_size, MAX_HAND_SIZE);
+        assert!(Deck::read_config("cards = 2345\ncolour = red").is_err());
+    }
+
+    #[test]
+    fn test_hand_length_mismatch() {
+        let deck = Deck::standard();
+        let err = read_input("32T3K 765\nT55J5J 684".lines(), &deck, Ruleset::Standard).unwrap_err();
+        assert_eq!(err.to_string(), "line 2, column 6: hand has 6 cards, but hands have 5");
+        let err = read_input("32T3 765".lines(), &deck, Ruleset::Standard).unwrap_err();
+        assert_eq!(err, ParseError { line: 1, column: 5, kind: ParseErrorKind::HandSize { found: 4, expected: 5 } });
+    }
+
+    #[test]
+    fn test_parse_errors() {
+        let deck = Deck::standard();
+        let parse = |line: &str| parse_line(line, 3, &deck);
+        assert_eq!(parse("32T3K 765"), Ok((cards("32T3K"), 765)));
+        assert_eq!(parse("32T3K \t 765 "), Ok((cards("32T3K"), 765)));
+        assert_eq!(parse("32x3K 765"), Err(ParseError { line: 3, column: 3, kind: ParseErrorKind::InvalidCard('x') }));
+        assert_eq!(parse("é2T3K 765").unwrap_err().to_string(), "line 3, column 1: invalid card 'é'");
+        assert_eq!(parse("32T3K"), Err(ParseError { line: 3, column: 6, kind: ParseErrorKind::MissingBid }));
+        assert_eq!(parse("32T3K   "), Err(ParseError { line: 3, column: 9, kind: ParseErrorKind::MissingBid }));
+        assert_eq!(parse("32T3K +76"), Err(ParseError { line: 3, column: 7, kind: ParseErrorKind::InvalidBid("+76".to_string()) }));
+        assert_eq!(parse("32T3K 7 6").unwrap_err().kind, ParseErrorKind::InvalidBid("7 6".to_string()));
+        assert_eq!(parse("32T3K 99999999999999999999").unwrap_err().column, 7);
+        assert_eq!(parse("").unwrap_err().kind, ParseErrorKind::HandSize { found: 0, expected: 5 });
+    }
+
+    #[test]
+    fn test_parser_does_not_panic() {
+        const ALPHABET: [char; 16] = ['A', 'K', 'J', 'T', '2', '9', '0', ' ', '\t', '\n', '\r', '-', '+', 'é', '🂡', '\u{0}'];
+        let mut rng = Rng(0xca4e1);
+        let decks = [Deck::standard(), Deck { hand_size: 1, ..Deck::standard() }, Deck::read_config("cards = é🂡\nhand size = 2\njoker = é").unwrap()];
+        for _ in 0..20000 {
+            let text: String = (0..rng.next(24)).map(|_| ALPHABET[rng.next(ALPHABET.len())]).collect();
+            for deck in &decks {
+                for ruleset in [Ruleset::Standard, Ruleset::JokersWild] {
+                    match read_input(text.lines(), deck, ruleset) {
+                        Ok(hands) => assert!(hands.iter().all(|hand| hand.cards.len() == deck.hand_size)),
+                        Err(err) => assert!(err.line >= 1 && err.column >= 1),
+                    }
+                }
+            }
+        }
+
+        // mutate single characters of the example
+        let example: Vec<char> = EXAMPLE.chars().collect();
+        for _ in 0..5000 {
+            let mut text = example.clone();
+            let i = rng.next(text.len());
+            text[i] = ALPHABET[rng.next(ALPHABET.len())];
+            let text: String = text.into_iter().collect();
+            let _ = read_input(text.lines(), &Deck::standard(), Ruleset::JokersWild);
+        }
+    }
+
+    // best_type_exhaustive tries every card of the deck for every joker at or after position from and keeps the strongest hand type
+    fn best_type_exhaustive(cards: &[Card], deck: &Deck, from: usize) -> HandType {
+        match (from..cards.len()).find(|i| deck.is_joker(cards[*i], Ruleset::JokersWild)) {
+            None => Hand::calculate_hand_type(cards, deck, Ruleset::Standard),
+            Some(joker) => {
+                let mut best = None;
+                for symbol in &deck.ranking {
+                    let mut assigned = cards.to_vec();
+                    assigned[joker] = Card(*symbol);
+                    best = best.max(Some(best_type_exhaustive(&assigned, deck, joker + 1)));
+                }
+                best.unwrap()
+            }
+        }
+    }
+
+    #[test]
+    fn test_explain() {
+        let deck = Deck::standard();
+        let mut hands = read_input(EXAMPLE.lines(), &deck, Ruleset::JokersWild).unwrap();
+        hands.sort();
+        let lines: Vec<String> = explain(&hands, &deck, Ruleset::JokersWild).iter().map(|e| e.to_string()).collect();
+        assert_eq!(
+            lines,
+            [
+                "rank 1: 32T3K (one pair), bid 765, winnings 765",
+                "rank 2: KK677 (two pairs), bid 28, winnings 56",
+                "rank 3: T55J5 as T5555 (four of a kind), bid 684, winnings 2052",
+                "rank 4: QQQJA as QQQQA (four of a kind), bid 483, winnings 1932",
+                "rank 5: KTJJT as KTTTT (four of a kind), bid 220, winnings 1100",
+            ]
+        );
+        let total: u64 = explain(&hands, &deck, Ruleset::JokersWild).iter().map(|e| e.winnings).sum();
+        assert_eq!(Some(total), total_winnings(&hands));
+
+        // ties between groups go to the stronger card, only jokers become the strongest card
+        assert_eq!(Hand::best_substitution(&cards("2JK2K"), &deck, Ruleset::JokersWild), cards("2KK2K"));
+        assert_eq!(Hand::best_substitution(&cards("JJJJJ"), &deck, Ruleset::JokersWild), cards("AAAAA"));
+        assert_eq!(Hand::best_substitution(&cards("JJJJJ"), &deck, Ruleset::Standard), cards("JJJJJ"));
+        let joker_last = Deck { ranking: "23456789TQKAJ".chars().collect(), ..Deck::standard() };
+        assert_eq!(Hand::best_substitution(&cards("JJJJJ"), &joker_last, Ruleset::JokersWild), cards("AAAAA"));
+        let only_jokers = Deck { ranking: vec!['J'], ..Deck::standard() };
+        assert_eq!(Hand::best_substitution(&cards("JJJJJ"), &only_jokers, Ruleset::JokersWild), cards("JJJJJ"));
+    }
+
+    #[test]
+    fn test_substitution_matches_exhaustive_search() {
+        let deck = Deck::standard();
+        let mut rng = Rng(0x7e5);
+        // draw mostly from few symbols so that jokers and groups are common
+        let symbols: Vec<char> = "JJJ2KKA".chars().chain(deck.ranking.iter().copied()).collect();
+        for _ in 0..3000 {
+            let hand: Vec<Card> = (0..5).map(|_| Card(symbols[rng.next(symbols.len())])).collect();
+            let best = best_type_exhaustive(&hand, &deck, 0);
+            assert_eq!(Hand::calculate_hand_type(&hand, &deck, Ruleset::JokersWild), best);
+            let substituted = Hand::best_substitution(&hand, &deck, Ruleset::JokersWild);
+            assert_eq!(Hand::calculate_hand_type(&substituted, &deck, Ruleset::Standard), best);
+        }
+        let six = Deck { hand_size: 6, ranking: "2345J".chars().collect(), joker: Some('J') };
+        for _ in 0..1000 {
+            let hand: Vec<Card> = (0..6).map(|_| Card(six.ranking[rng.next(six.ranking.len())])).collect();
+            let substituted = Hand::best_substitution(&hand, &six, Ruleset::JokersWild);
+            assert_eq!(Hand::calculate_hand_type(&substituted, &six, Ruleset::Standard), best_type_exhaustive(&hand, &six, 0));
+        }
+    }
+
+    #[test]
+    fn test_hand_type_probabilities() {
+        let deck = Deck::standard();
+        let distribution = Distribution::new(&deck, Ruleset::Standard, 0, &mut Rng::new(1));
+        assert!(distribution.exhaustive);
+        assert_eq!(distribution.hands.len(), 371293);
+        let counts: Vec<(String, u64)> = distribution
+            .type_probabilities()
+            .iter()
+            .map(|(typ, probability)| (typ.to_string(), (probability * 371293.0).round() as u64))
+            .collect();
+        let expected = [
+            ("high card", 154440),
+            ("one pair", 171600),
+            ("two pairs", 25740),
+            ("three of a kind", 17160),
+            ("full house", 1560),
+            ("four of a kind", 780),
+            ("five of a kind", 13),
+        ];
+        assert_eq!(counts, expected.map(|(name, count)| (name.to_string(), count)));
+
+        // the strongest hand only ties with itself, nothing is weaker than the weakest
+        let hand = |cards_: &str, bid| Hand::new(cards(cards_), bid, &deck, Ruleset::Standard);
+        assert_eq!(distribution.odds(&hand("AAAAA", 0)), (371292.0 / 371293.0, 1.0 / 371293.0));
+        assert_eq!(distribution.win_probability(&hand("23456", 0), 1), 0.0);
+        assert_eq!(distribution.win_probability(&hand("23456", 0), 0), 1.0);
+        let win = distribution.win_probability(&hand("KKKKQ", 0), 3);
+        assert_eq!(win, distribution.win_probability(&hand("KKKKQ", 0), 1).powi(3));
+        assert!(win > 0.99 && win < 1.0);
+        assert_eq!(distribution.expected_winnings(&hand("AAAAA", 10), 0), 10.0);
+        // the weakest hand only ties with itself
+        assert_eq!(distribution.expected_winnings(&hand("23456", 10), 4), 10.0 * (1.0 + 2.0 / 371293.0));
+    }
+
+    #[test]
+    fn test_odds_tiny_deck() {
+        // hands ab, ba: high card, aa and bb: one pair
+        let deck = Deck { ranking: vec!['a', 'b'], hand_size: 2, joker: Some('a') };
+        let distribution = Distribution::new(&deck, Ruleset::Standard, 0, &mut Rng::new(1));
+        let names: Vec<(String, f64)> = distribution.type_probabilities().iter().map(|(typ, p)| (typ.to_string(), *p)).collect();
+        assert_eq!(names, [("high card".to_string(), 0.5), ("one pair".to_string(), 0.5)]);
+        // with jokers every hand pairs up, ties are broken by the cards
+        let distribution = Distribution::new(&deck, Ruleset::JokersWild, 0, &mut Rng::new(1));
+        assert_eq!(distribution.type_probabilities(), [(HandType { groups: vec![2] }, 1.0)]);
+        let ab = Hand::new(cards("ab"), 6, &deck, Ruleset::JokersWild);
+        assert_eq!(distribution.odds(&ab), (0.25, 0.25));
+        // rank 1 + 2 opponents * (1/4 weaker + 1/8 tied)
+        assert_eq!(distribution.expected_winnings(&ab, 2), 6.0 * 1.75);
+        // opponents beyond i32::MAX must not wrap into a negative exponent
+        assert_eq!(distribution.win_probability(&ab, 2), 0.0625);
+        assert_eq!(distribution.win_probability(&ab, u32::MAX), 0.0);
+        let bb = Hand::new(cards("bb"), 6, &deck, Ruleset::JokersWild);
+        assert_eq!(distribution.win_probability(&bb, u32::MAX), 0.0);
+    }
+
+    #[test]
+    fn test_sampled_odds() {
+        let deck = Deck { hand_size: 7, ..Deck::standard() };
+        let sample = |seed| Distribution::new(&deck, Ruleset::JokersWild, 20000, &mut Rng::new(seed)).type_probabilities();
+        assert!(!Distribution::new(&deck, Ruleset::JokersWild, 10, &mut Rng::new(1)).exhaustive);
+        // the same seed draws the same hands
+        assert_eq!(sample(7), sample(7));
+        assert_ne!(sample(7), sample(8));
+
+        // sampling a four card deck comes close to the exact probabilities
+        let deck = Deck { hand_size: 4, ..Deck::standard() };
+        let mut hands = Vec::new();
+        let mut rng = Rng::new(3);
+        for _ in 0..20000 {
+            let cards = (0..4).map(|_| Card(deck.ranking[rng.next(13)])).collect();
+            hands.push(Hand::new(cards, 0, &deck, Ruleset::Standard));
+        }
+        hands.sort();
+        let sampled = Distribution { hands, exhaustive: false }.type_probabilities();
+        let exact = Distribution::new(&deck, Ruleset::Standard, 0, &mut Rng::new(1)).type_probabilities();
+        for (typ, exact) in exact {
+            let sampled = sampled.iter().find(|(sampled_type, _)| *sampled_type == typ).map_or(0.0, |(_, p)| *p);
+            assert!((sampled - exact).abs() < 0.01, "{}: {} vs {}", typ, sampled, exact);
+        }
+    }
+
+    #[test]
+    fn test_standings() {
+        let deck = Deck::standard();
+        let mut hands = read_input(EXAMPLE.lines(), &deck, Ruleset::JokersWild).unwrap();
+        hands.sort();
+        let table = standings(&hands, Ruleset::JokersWild);
+        assert_eq!(table.iter().map(|standing| standing.winnings).sum::<u64>(), total_winnings(&hands).unwrap());
+        assert!(table.iter().all(|standing| standing.tie.is_none()));
+        assert_eq!(
+            write_standings(&table[..2], StandingsFormat::Text),
+            concat!(
+                "ruleset       rank  hand   type              bid      winnings\n",
+                "jokers wild      1  32T3K  one pair          765           765\n",
+                "jokers wild      2  KK677  two pairs          28            56\n",
+            )
+        );
+        assert_eq!(
+            write_standings(&table[3..], StandingsFormat::Csv),
+            "ruleset,rank,hand,type,bid,winnings,tie\njokers wild,4,QQQJA,four of a kind,483,1932,\njokers wild,5,KTJJT,four of a kind,220,1100,\n"
+        );
+        assert_eq!(
+            write_standings(&table[4..], StandingsFormat::Json),
+            "[\n  {\"ruleset\": \"jokers wild\", \"rank\": 5, \"hand\": \"KTJJT\", \"type\": \"four of a kind\", \"bid\": 220, \"winnings\": 1100, \"tie\": null}\n]\n"
+        );
+        assert_eq!(write_standings(&[], StandingsFormat::Json), "[]\n");
+
+        // the standings of both rulesets form a single document
+        let mut standard = read_input(EXAMPLE.lines(), &deck, Ruleset::Standard).unwrap();
+        standard.sort();
+        let both: Vec<Standing> = standings(&standard, Ruleset::Standard).into_iter().chain(table).collect();
+        let csv = write_standings(&both, StandingsFormat::Csv);
+        assert_eq!(csv.lines().count(), 11);
+        assert_eq!(csv.lines().filter(|line| line.starts_with("standard,")).count(), 5);
+        let json = write_standings(&both, StandingsFormat::Json);
+        assert_eq!((json.matches('[').count(), json.matches("\"ruleset\": \"standard\"").count()), (1, 5));
+    }
+
+    #[test]
+    fn test_standings_ties() {
+        let deck = Deck::standard();
+        let mut hands = read_input("KK677 1\n32T3K 2\nKK677 3\nKK677 4\nQQQJA 5".lines(), &deck, Ruleset::Standard).unwrap();
+        hands.sort();
+        let table = standings(&hands, Ruleset::Standard);
+        let summary: Vec<_> = table.iter().map(|standing| (standing.rank, standing.bid, standing.tie)).collect();
+        // the tied KK677 hands are ranked in input order
+        assert_eq!(summary, [(1, 2, None), (2, 1, Some((2, 4))), (3, 3, Some((2, 4))), (4, 4, Some((2, 4))), (5, 5, None)]);
+        assert!(write_standings(&table, StandingsFormat::Text).contains("standard      3  KK677  two pairs                 3             9  tied with ranks 2-4\n"));
+        assert!(write_standings(&table, StandingsFormat::Csv).contains("\nstandard,3,KK677,two pairs,3,9,2-4\n"));
+        assert!(write_standings(&table, StandingsFormat::Json).contains("\"tie\": [2, 4]"));
+
+        // hands that only tie once jokers are wild are still ordered by their cards
+        let mut hands = read_input("JKKK2 1\nQQQQ2 2".lines(), &deck, Ruleset::JokersWild).unwrap();
+        hands.sort();
+        assert!(standings(&hands, Ruleset::JokersWild).iter().all(|standing| standing.tie.is_none()));
+        assert_eq!(csv_field("a,\"b\""), "\"a,\"\"b\"\"\"");
+    }
+
+    fn random_hands(rng: &mut Rng, deck: &Deck, count: usize) -> Vec<(Vec<Card>, u64)> {
+        // few symbols give plenty of equal groups and exact ties
+        let symbols = &deck.ranking[..std::cmp::min(deck.ranking.len(), 1 + rng.next(deck.ranking.len()))];
+        (0..count).map(|_| ((0..deck.hand_size).map(|_| Card(symbols[rng.next(symbols.len())])).collect(), rng.next(1000) as u64)).collect()
+    }
+
+    #[test]
+    fn test_packed_keys_match_ord() {
+        let mut rng = Rng::new(0x4ad1);
+        let decks = [
+            Deck::standard(),
+            Deck { hand_size: 6, ..Deck::standard() },
+            Deck { hand_size: 1, ranking: vec!['a', 'b', 'c'], joker: None },
+            Deck::read_config("cards = 0123456789abcdefghijklmnopqrstuvwxyz\nhand size = 9\njoker = a").unwrap(),
+        ];
+        for deck in &decks {
+            let layout = KeyLayout::new(deck).unwrap();
+            for ruleset in [Ruleset::Standard, Ruleset::JokersWild] {
+                for _ in 0..20 {
+                    let count = rng.next(300);
+                    let input = random_hands(&mut rng, deck, count);
+                    let mut hands: Vec<Hand> = input.iter().map(|(cards, bid)| Hand::new(cards.clone(), *bid, deck, ruleset)).collect();
+                    let mut ranking = StreamRanking::new(layout, 7);
+                    for hand in &hands {
+                        ranking.push(hand);
+                    }
+                    for (a, b) in hands.iter().zip(hands.iter().skip(1)) {
+                        assert_eq!(layout.key(a).cmp(&layout.key(b)), a.cmp(b));
+                    }
+                    let ranks: Vec<usize> = (0..hands.len()).map(|index| ranking.rank_of(index)).collect();
+
+                    let mut entries = ranking.entries.clone();
+                    radix_sort(&mut entries, layout.bits());
+                    // remember the input position to compare the order of tied hands as well
+                    let mut indexed: Vec<(usize, &Hand)> = hands.iter().enumerate().collect();
+                    indexed.sort_by(|a, b| a.1.cmp(b.1));
+                    let sorted_keys: Vec<(u128, u64)> = indexed.iter().map(|(_, hand)| (layout.key(hand), hand.bid)).collect();
+                    assert_eq!(entries, sorted_keys);
+                    for (rank, (index, _)) in (1..).zip(indexed.iter()) {
+                        assert_eq!(ranks[*index], rank);
+                    }
+
+                    let top: Vec<(usize, Vec<Card>, u64)> =
+                        indexed.iter().enumerate().rev().take(7).map(|(i, (_, hand))| (i + 1, hand.cards.clone(), hand.bid)).collect();
+                    assert_eq!(ranking.top(), top);
+
+                    hands.sort();
+                    assert_eq!(ranking.total_winnings(), total_winnings(&hands));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn test_stream_ranking_input() {
+        let deck = Deck::standard();
+        let input = include_str!("input.txt");
+        let mut ranking = StreamRanking::new(KeyLayout::new(&deck).unwrap(), 1);
+        for (i, line) in input.lines().enumerate() {
+            let (cards, bid) = parse_line(line, i + 1, &deck).unwrap();
+            ranking.push(&Hand::new(cards, bid, &deck, Ruleset::JokersWild));
+        }
+        assert_eq!(ranking.total_winnings(), Some(251224870));
+        assert_eq!(ranking.top(), [(1000, cards("AJAAA"), 924)]);
+
+        assert_eq!(KeyLayout::new(&deck).unwrap().bits(), 35);
+        let huge = Deck { hand_size: 20, ..Deck::standard() };
+        assert_eq!(KeyLayout::new(&huge), None);
+    }
+
+    #[test]
+    fn test_text_forms() {
+        let hand: Hand = "KTJJT".parse().unwrap();
+        assert_eq!(hand.to_string(), "KTJJT");
+        assert_eq!(hand.typ.to_string(), "two pairs");
+        let wild = Hand::parse("KTJJT", &Deck::standard(), Ruleset::JokersWild).unwrap();
+        assert_eq!(wild.typ, "four of a kind".parse().unwrap());
+        assert_eq!(wild.to_string(), "KTJJT");
+        assert_eq!("KTJJT 220".parse::<Hand>().unwrap_err(), ParseError { line: 1, column: 6, kind: ParseErrorKind::InvalidCard(' ') });
+        assert_eq!(parse_line("KTJJT 220", 1, &Deck::standard()), Ok((cards("KTJJT"), 220)));
+
+        assert_eq!("K".parse::<Card>(), Ok(Card('K')));
+        assert_eq!(Card('K').to_string(), "K");
+        assert_eq!("x".parse::<Card>().unwrap_err().kind, ParseErrorKind::InvalidCard('x'));
+        assert_eq!("".parse::<Card>().unwrap_err().kind, ParseErrorKind::MissingCard);
+        assert_eq!("KK".parse::<Card>().unwrap_err(), ParseError { line: 1, column: 2, kind: ParseErrorKind::UnexpectedCharacter('K') });
+        assert_eq!("KTJJ".parse::<Hand>().unwrap_err().kind, ParseErrorKind::HandSize { found: 4, expected: 5 });
+
+        assert_eq!("full house".parse::<HandType>(), Ok(HandType { groups: vec![3, 2] }));
+        assert_eq!(HandType::parse("full house", 7), Some(HandType { groups: vec![3, 2, 1, 1] }));
+        assert_eq!(HandType::parse("three pairs", 6), Some(HandType { groups: vec![2, 2, 2] }));
+        assert_eq!(HandType::parse("groups of 4 and 3", 9), Some(HandType { groups: vec![4, 3, 1, 1] }));
+        assert_eq!(HandType::parse("twelve of a kind", 12), None);
+        assert_eq!(HandType::parse("12 of a kind", 12), Some(HandType { groups: vec![12] }));
+        for name in ["six of a kind", "groups of 2 and 2", "groups of 3 and 4", "one of a kind", "1 pair", "four pairs", "", "99999999999999999999 pairs"] {
+            assert_eq!(name.parse::<HandType>().unwrap_err().kind, ParseErrorKind::UnknownHandType(name.to_string()));
+        }
+    }
+
+    #[test]
+    fn test_text_forms_round_trip() {
+        let mut rng = Rng::new(0x70f0);
+        for card in Deck::standard().ranking {
+            assert_eq!(Card(card).to_string().parse::<Card>(), Ok(Card(card)));
+        }
+        for _ in 0..2000 {
+            let hand_size = 1 + rng.next(12);
+            let deck = Deck { hand_size, ..Deck::standard() };
+            let ruleset = if rng.next(2) == 0 { Ruleset::Standard } else { Ruleset::JokersWild };
+            let (cards, bid) = random_hands(&mut rng, &deck, 1).remove(0);
+            let hand = Hand::new(cards, bid, &deck, ruleset);
+
+            let text = hand.to_string();
+            let parsed = Hand::parse(&text, &deck, ruleset).unwrap();
+            assert_eq!((&parsed.cards, &parsed.typ, &parsed.strengths), (&hand.cards, &hand.typ, &hand.strengths));
+            assert_eq!(parsed.to_string(), text);
+            assert_eq!(parse_line(&format!("{} {}", text, bid), 1, &deck), Ok((hand.cards.clone(), bid)));
+
+            assert_eq!(HandType::parse(&hand.typ.to_string(), hand_size), Some(hand.typ.clone()), "{}", hand.typ);
+            if hand_size == 5 {
+                assert_eq!(hand.typ.to_string().parse::<HandType>(), Ok(hand.typ.clone()));
+            }
+        }
+    }
+
+    #[test]
+    fn test_winnings_invariants() {
+        let mut rng = Rng::new(0xb1d);
+        let deck = Deck::standard();
+        for _ in 0..300 {
+            let ruleset = if rng.next(2) == 0 { Ruleset::Standard } else { Ruleset::JokersWild };
+            let count = 1 + rng.next(60);
+            let mut drawn = random_hands(&mut rng, &deck, count);
+            // distinct cards, so the order of the sorted hands does not depend on the input order
+            drawn.sort();
+            drawn.dedup_by(|a, b| a.0 == b.0);
+            let mut hands: Vec<Hand> = drawn.into_iter().map(|(cards, bid)| Hand::new(cards, bid, &deck, ruleset)).collect();
+            hands.sort();
+            let total = total_winnings(&hands).unwrap();
+
+            // shuffling the input changes nothing
+            let mut shuffled: Vec<Hand> = hands.iter().map(|hand| Hand::new(hand.cards.clone(), hand.bid, &deck, ruleset)).collect();
+            for i in (1..shuffled.len()).rev() {
+                shuffled.swap(i, rng.next(i + 1));
+            }
+            shuffled.sort();
+            assert_eq!(total_winnings(&shuffled), Some(total));
+
+            // raising a bid raises the total by the rank of the hand times the raise
+            let index = rng.next(hands.len());
+            let raise = rng.next(100) as u64;
+            hands[index].bid += raise;
+            assert_eq!(total_winnings(&hands), Some(total + (index as u64 + 1) * raise));
+
+            // jokers only ever help the hand they are in
+            for hand in &hands {
+                assert!(Hand::calculate_hand_type(&hand.cards, &deck, Ruleset::JokersWild) >= Hand::calculate_hand_type(&hand.cards, &deck, Ruleset::Standard));
+            }
+        }
+    }
+
+    #[test]
+    fn test_total_winnings_overflow() {
+        let deck = Deck::standard();
+        let bid = u64::MAX / 4;
+        let input = format!("32T3K 386\nT55J7 {}\nT55J5 {}", bid, bid);
+        let mut hands = read_input(input.lines(), &deck, Ruleset::Standard).unwrap();
+        hands.sort();
+        assert_eq!(total_winnings(&hands), None);
+        assert_eq!(total_winnings(&hands[..2]), Some(386 + 2 * bid));
+
+        let mut ranking = StreamRanking::new(KeyLayout::new(&deck).unwrap(), 0);
+        for hand in &hands {
+            ranking.push(hand);
+        }
+        assert_eq!(ranking.total_winnings(), None);
+    }
+}
@@ -0,0 +1,19 @@
+#![no_main]
+#![allow(dead_code)]
+
+use libfuzzer_sys::fuzz_target;
+
+include!("../../2023/07/camelcards.rs");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    // read_input runs parse_line on every line
+    for ruleset in [Ruleset::Standard, Ruleset::JokersWild] {
+        if let Ok(mut hands) = read_input(text.lines(), &Deck::standard(), ruleset) {
+            hands.sort();
+            total_winnings(&hands);
+        }
+    }
+});
@@ -0,0 +1,14 @@
+#![no_main]
+#![allow(dead_code)]
+
+use libfuzzer_sys::fuzz_target;
+
+include!("../../2023/02/cube_conundrum.rs");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    // evaluate_games runs eval_game_part_1 and eval_game_part_2 on every line
+    let _ = evaluate_games(text.lines().map(|line| Ok(line.to_string())));
+});
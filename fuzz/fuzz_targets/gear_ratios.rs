@@ -0,0 +1,17 @@
+#![no_main]
+#![allow(dead_code)]
+
+use libfuzzer_sys::fuzz_target;
+
+include!("../../2023/03/gear_ratios.rs");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(matrix) = read_matrix(text.lines().map(|line| Ok(line.to_string()))) {
+        find_all_numbers(&matrix);
+        find_gears(&matrix);
+    }
+    let _ = scan_rows(text.lines().map(|line| Ok(line.to_string())));
+});
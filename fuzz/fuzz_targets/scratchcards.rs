@@ -0,0 +1,15 @@
+#![no_main]
+#![allow(dead_code)]
+
+use libfuzzer_sys::fuzz_target;
+
+include!("../../2023/04/scratchcards.rs");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    // calculate_wins runs parse_line on every line
+    let _ = calculate_wins(text.lines().map(|line| Ok(line.to_string())));
+    let _ = calculate_wins_unordered(text.lines().map(|line| Ok(line.to_string())));
+});
@@ -0,0 +1,20 @@
+#![no_main]
+#![allow(dead_code)]
+
+use libfuzzer_sys::fuzz_target;
+
+include!("../../2023/05/seedlocation.rs");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    if let Ok(input) = read_maps(text.lines().map(|line| Ok(line.to_string()))) {
+        let _ = part_1(&input);
+        let _ = part_2(&input);
+    }
+    if let Ok(input) = read_json(text) {
+        let _ = part_1(&input);
+        let _ = part_2(&input);
+    }
+});
@@ -0,0 +1,18 @@
+#![no_main]
+#![allow(dead_code)]
+
+use libfuzzer_sys::fuzz_target;
+
+include!("../../2023/06/wait_for_it.rs");
+
+fuzz_target!(|data: &[u8]| {
+    let Ok(text) = std::str::from_utf8(data) else {
+        return;
+    };
+    // read_races runs read_number_row on both rows
+    for kerning in [Kerning::Separate, Kerning::Joined] {
+        if let Ok(races) = read_races(text.lines(), kerning) {
+            calculate_number_of_wins_multiplied(&races, &Physics::standard());
+        }
+    }
+});